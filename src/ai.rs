@@ -0,0 +1,242 @@
+//! computer controlled snakes
+
+use crate::map::Map;
+use crate::snake::{can_turn, Direction, Snake};
+use std::collections::VecDeque;
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// manhattan distance between two cells
+pub fn manhattan(a: (i32, i32), b: (i32, i32)) -> i32 {
+    (a.0 - b.0).abs() + (a.1 - b.1).abs()
+}
+
+/// greedy one step lookahead: head for the nearest food, never into a wall,
+/// the snake's own body or any of the `others`. keeps going straight if
+/// every move is unsafe
+pub fn ai_choose_direction(
+    snake: &Snake,
+    others: &[&Snake],
+    food: &[(i32, i32)],
+    map: &Map,
+) -> Direction {
+    let current = snake.head.dir;
+    let head = snake.head_tile();
+
+    let target = match food.iter().min_by_key(|&&f| manhattan(head, f)) {
+        Some(&f) => f,
+        None => return current,
+    };
+
+    // straight first so ties keep the current heading
+    let candidates = [
+        current,
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    candidates
+        .iter()
+        .copied()
+        .filter(|&d| can_turn(current, d))
+        .filter(|&d| {
+            let (dx, dy) = d.offset();
+            let (x, y) = (head.0 + dx, head.1 + dy);
+            !map.is_tile_blocked(x, y)
+                && !snake.tail_occupies(x, y)
+                && !others.iter().any(|o| o.occupies(x, y))
+        })
+        .min_by_key(|&d| {
+            let (dx, dy) = d.offset();
+            manhattan((head.0 + dx, head.1 + dy), target)
+        })
+        .unwrap_or(current)
+}
+
+/// direction of a one step move from `from` to `to`, None if they aren't
+/// next to each other
+pub fn direction_to(from: (i32, i32), to: (i32, i32)) -> Option<Direction> {
+    match (to.0 - from.0, to.1 - from.1) {
+        (0, -1) => Some(Direction::Up),
+        (0, 1) => Some(Direction::Down),
+        (-1, 0) => Some(Direction::Left),
+        (1, 0) => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+/// shortest path (breadth first) from `start` to `goal` around walls and
+/// `occupied` cells, as the cells to step through after `start` up to and
+/// including `goal`. None if the goal can't be reached
+pub fn find_path(
+    map: &Map,
+    start: (i32, i32),
+    goal: (i32, i32),
+    occupied: &[(i32, i32)],
+) -> Option<Vec<(i32, i32)>> {
+    if !map.in_bounds(start.0, start.1) || !map.in_bounds(goal.0, goal.1) {
+        return None;
+    }
+
+    let index = |(x, y): (i32, i32)| (x + map.width * y) as usize;
+    // cell each visited cell was reached from
+    let mut came_from: Vec<Option<(i32, i32)>> = vec![None; (map.width * map.height) as usize];
+    let mut seen = vec![false; came_from.len()];
+    let mut queue = VecDeque::new();
+    seen[index(start)] = true;
+    queue.push_back(start);
+
+    while let Some(cell) = queue.pop_front() {
+        if cell == goal {
+            let mut path = vec![];
+            let mut at = goal;
+            while at != start {
+                path.push(at);
+                at = came_from[index(at)]?;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for (dx, dy) in [(0, -1), (0, 1), (-1, 0), (1, 0)].iter() {
+            let next = (cell.0 + dx, cell.1 + dy);
+            if !map.in_bounds(next.0, next.1) || seen[index(next)] {
+                continue;
+            }
+            if map.is_tile_blocked(next.0, next.1) || occupied.contains(&next) {
+                continue;
+            }
+            seen[index(next)] = true;
+            came_from[index(next)] = Some(cell);
+            queue.push_back(next);
+        }
+    }
+
+    None
+}
+
+/// demo mode autopilot: follow the shortest path to the closest reachable
+/// food, or make any move that doesn't crash if no food can be reached
+pub fn demo_direction(snake: &Snake, food: &[(i32, i32)], map: &Map) -> Direction {
+    let current = snake.head.dir;
+    let head = snake.head_tile();
+    let occupied: Vec<(i32, i32)> = snake.tail.iter().map(|t| t.cell()).collect();
+
+    let step = food
+        .iter()
+        .filter_map(|&f| find_path(map, head, f, &occupied))
+        .min_by_key(|p| p.len())
+        .and_then(|p| p.first().copied())
+        .and_then(|next| direction_to(head, next))
+        .filter(|&d| can_turn(current, d));
+    if let Some(dir) = step {
+        return dir;
+    }
+
+    // straight first, then any other safe turn
+    let candidates = [
+        current,
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+    candidates
+        .iter()
+        .copied()
+        .filter(|&d| can_turn(current, d))
+        .find(|&d| {
+            let (dx, dy) = d.offset();
+            let (x, y) = (head.0 + dx, head.1 + dy);
+            !map.is_tile_blocked(x, y) && !occupied.contains(&(x, y))
+        })
+        .unwrap_or(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::blank_map;
+
+    /// walls round the edge of a 10x10 map
+    fn open_map() -> Map {
+        Map::new(10, 10, blank_map(10, 10))
+    }
+
+    #[test]
+    fn heads_for_the_nearest_food() {
+        let map = open_map();
+        let snake = Snake::with_length((4, 4), Direction::Right, 1);
+        let food = [(4, 1), (8, 8)];
+        assert_eq!(ai_choose_direction(&snake, &[], &food, &map), Direction::Up);
+    }
+
+    #[test]
+    fn keeps_straight_on_a_tie() {
+        let map = open_map();
+        let snake = Snake::with_length((2, 4), Direction::Right, 1);
+        assert_eq!(
+            ai_choose_direction(&snake, &[], &[(6, 4)], &map),
+            Direction::Right
+        );
+    }
+
+    #[test]
+    fn never_turns_into_a_wall() {
+        let map = open_map();
+        // food straight past the wall to the left, up is the way round
+        let snake = Snake::with_length((1, 4), Direction::Up, 1);
+        let dir = ai_choose_direction(&snake, &[], &[(0, 4)], &map);
+        assert_ne!(dir, Direction::Left);
+    }
+
+    #[test]
+    fn never_turns_back_on_itself() {
+        let map = open_map();
+        let snake = Snake::with_length((5, 4), Direction::Right, 3);
+        let dir = ai_choose_direction(&snake, &[], &[(1, 4)], &map);
+        assert_ne!(dir, Direction::Left);
+    }
+
+    #[test]
+    fn steers_round_other_snakes() {
+        let map = open_map();
+        let snake = Snake::with_length((4, 4), Direction::Right, 1);
+        // the other snake lies right across the way to the food
+        let other = Snake::with_length((5, 4), Direction::Up, 1);
+        let dir = ai_choose_direction(&snake, &[&other], &[(8, 4)], &map);
+        assert_ne!(dir, Direction::Right);
+    }
+
+    #[test]
+    fn manhattan_distance() {
+        assert_eq!(manhattan((1, 2), (4, 0)), 5);
+        assert_eq!(manhattan((3, 3), (3, 3)), 0);
+    }
+
+    #[test]
+    fn demo_takes_the_shortest_path() {
+        let map = open_map();
+        let snake = Snake::with_length((2, 4), Direction::Right, 1);
+        assert_eq!(demo_direction(&snake, &[(2, 2)], &map), Direction::Up);
+    }
+
+    #[test]
+    fn demo_goes_round_its_own_tail() {
+        let map = open_map();
+        // food straight behind, the tail is in the way
+        let snake = Snake::with_length((5, 4), Direction::Right, 3);
+        let dir = demo_direction(&snake, &[(1, 4)], &map);
+        assert!(dir == Direction::Up || dir == Direction::Down);
+    }
+
+    #[test]
+    fn demo_without_food_turns_away_from_walls() {
+        let map = open_map();
+        let snake = Snake::with_length((8, 4), Direction::Right, 1);
+        assert_eq!(demo_direction(&snake, &[], &map), Direction::Up);
+    }
+}
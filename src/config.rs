@@ -0,0 +1,421 @@
+//! tunables read once at startup from a small toml file, so the game can be
+//! changed without a rebuild
+
+use crate::theme::ThemeName;
+use crate::{MAX_FRAMERATE, TILE_H, TILE_W};
+use std::io::{Error, ErrorKind, Result};
+
+/// range of px a tile can be drawn at, small enough to still see a snake
+/// and big enough for a window to hold a map
+const MIN_TILE_SIZE: u32 = 8;
+const MAX_TILE_SIZE: u32 = 64;
+
+// -----------------------------------
+// CONFIG
+// -----------------------------------
+/// everything the config file can set, `Default` is the game as shipped
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// game size in px, a whole number of tiles across and down
+    pub width: u32,
+    pub height: u32,
+    /// px a tile takes up on screen, the window is scaled from the game
+    /// size to fit
+    pub tile_size: u32,
+    /// frames drawn per second at most, moves keep their pace at any rate
+    pub framerate: u32,
+    /// move speed as a multiple of the difficulty's, 2.0 is twice as fast
+    pub speed: f32,
+    /// fewest ms between moves however long the snake gets, raising it caps
+    /// how fast the game can go
+    pub min_interval_ms: i32,
+    /// tiles a snake covers when it spawns, head included
+    pub start_length: usize,
+    /// wrap round the map edges, None leaves it to the difficulty
+    pub wrap: Option<bool>,
+    pub show_grid: bool,
+    pub theme: ThemeName,
+    /// keep the view on the head, for maps bigger than the window
+    pub follow_camera: bool,
+    /// no screen shake, flashing, pulsing or particles
+    pub reduced_motion: bool,
+    /// seconds on the menu with no input before the demo plays itself,
+    /// 0 never starts it
+    pub demo_idle_secs: u32,
+    /// slide the snake between cells instead of jumping a tile per move,
+    /// only the drawing changes, collisions stay on the grid
+    pub smooth_movement: bool,
+    /// the snake moves one tile per movement key press instead of on the clock
+    pub turn_based: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            tile_size: TILE_W as u32,
+            framerate: 30,
+            speed: 1.0,
+            min_interval_ms: 40,
+            start_length: 1,
+            wrap: None,
+            show_grid: false,
+            theme: ThemeName::Classic,
+            follow_camera: false,
+            reduced_motion: false,
+            demo_idle_secs: 30,
+            smooth_movement: true,
+            turn_based: false,
+        }
+    }
+}
+
+impl Config {
+    /// window size in px, the game's tiles drawn at tile_size
+    pub fn window_size(&self) -> (u32, u32) {
+        (
+            self.width / TILE_W as u32 * self.tile_size,
+            self.height / TILE_H as u32 * self.tile_size,
+        )
+    }
+
+    /// ms of idling on the menu before the demo starts, None if it never does
+    pub fn demo_idle_ms(&self) -> Option<i32> {
+        match self.demo_idle_secs {
+            0 => None,
+            secs => Some(secs.saturating_mul(1000).min(i32::MAX as u32) as i32),
+        }
+    }
+}
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// `key = value` lines, `#` comments and blank lines skipped. keys left out
+/// keep their default, an unknown key or a bad value fails the whole file
+pub fn load_config(path: &str) -> Result<Config> {
+    let text = std::fs::read_to_string(path)?;
+    let bad = |line: usize, msg: String| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("{}:{}: {}", path, line, msg),
+        )
+    };
+
+    let mut config = Config::default();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut parts = line.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = match parts.next() {
+            Some(v) => v.trim(),
+            None => return Err(bad(i + 1, format!("expected key = value, got {:?}", line))),
+        };
+        set_key(&mut config, key, value).map_err(|msg| bad(i + 1, msg))?;
+    }
+    Ok(config)
+}
+
+fn set_key(config: &mut Config, key: &str, value: &str) -> std::result::Result<(), String> {
+    let expected = |what: &str| format!("{} should be {}, got {}", key, what, value);
+    let tiles = |v: &str, tile: f32| match v.parse::<u32>() {
+        Ok(px) if px > 0 && px % tile as u32 == 0 => Ok(px),
+        _ => Err(expected(&format!("a multiple of {}", tile))),
+    };
+    let boolean = |v: &str| v.parse::<bool>().map_err(|_| expected("true or false"));
+
+    match key {
+        "width" => config.width = tiles(value, TILE_W)?,
+        "height" => config.height = tiles(value, TILE_H)?,
+        "tile_size" => {
+            config.tile_size = match value.parse::<u32>() {
+                Ok(px) if (MIN_TILE_SIZE..=MAX_TILE_SIZE).contains(&px) => px,
+                _ => {
+                    return Err(expected(&format!(
+                        "between {} and {}",
+                        MIN_TILE_SIZE, MAX_TILE_SIZE
+                    )))
+                }
+            }
+        }
+        "framerate" => {
+            config.framerate = match value.parse::<u32>() {
+                Ok(fps) if fps > 0 && fps <= MAX_FRAMERATE => fps,
+                _ => return Err(expected(&format!("between 1 and {}", MAX_FRAMERATE))),
+            }
+        }
+        "speed" => {
+            config.speed = match value.parse::<f32>() {
+                Ok(s) if s > 0.0 && s.is_finite() => s,
+                _ => return Err(expected("a number above 0")),
+            }
+        }
+        "min_interval_ms" => {
+            config.min_interval_ms = match value.parse::<i32>() {
+                Ok(ms) if ms > 0 => ms,
+                _ => return Err(expected("above 0")),
+            }
+        }
+        "start_length" => {
+            config.start_length = match value.parse::<usize>() {
+                Ok(n) if n > 0 => n,
+                _ => return Err(expected("at least 1")),
+            }
+        }
+        "demo_idle_secs" => {
+            config.demo_idle_secs = value
+                .parse::<u32>()
+                .map_err(|_| expected("a whole number of seconds"))?
+        }
+        "wrap" => config.wrap = Some(boolean(value)?),
+        "show_grid" => config.show_grid = boolean(value)?,
+        "follow_camera" => config.follow_camera = boolean(value)?,
+        "reduced_motion" => config.reduced_motion = boolean(value)?,
+        "smooth_movement" => config.smooth_movement = boolean(value)?,
+        "turn_based" => config.turn_based = boolean(value)?,
+        "theme" => {
+            let name = value.trim_matches('"');
+            config.theme = ThemeName::from_name(name)
+                .ok_or_else(|| expected("\"classic\", \"dark\" or \"high_contrast\""))?;
+        }
+        _ => return Err(format!("unknown key {:?}", key)),
+    }
+    Ok(())
+}
+
+/// the value after `flag` in the args, if the flag was given
+pub fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .map(|i| args.get(i + 1).map(String::as_str).unwrap_or(""))
+}
+
+/// window size in px from --width / --height, a whole number of tiles
+/// across and down, `default` for any not given
+pub fn parse_window_size(
+    args: &[String],
+    default: (u32, u32),
+) -> std::result::Result<(u32, u32), String> {
+    let parse = |flag: &str, default: u32, tile: u32| match flag_value(args, flag) {
+        None => Ok(default),
+        Some(v) => match v.parse::<u32>() {
+            Ok(px) if px > 0 && px % tile == 0 => Ok(px),
+            _ => Err(format!(
+                "{} must be a multiple of {}, got {:?}",
+                flag, tile, v
+            )),
+        },
+    };
+    Ok((
+        parse("--width", default.0, TILE_W as u32)?,
+        parse("--height", default.1, TILE_H as u32)?,
+    ))
+}
+
+/// frame limit from --fps, 1 to MAX_FRAMERATE, `default` if not given
+pub fn parse_framerate(args: &[String], default: u32) -> std::result::Result<u32, String> {
+    match flag_value(args, "--fps") {
+        None => Ok(default),
+        Some(v) => match v.parse::<u32>() {
+            Ok(fps) if fps > 0 && fps <= MAX_FRAMERATE => Ok(fps),
+            _ => Err(format!(
+                "--fps must be between 1 and {}, got {:?}",
+                MAX_FRAMERATE, v
+            )),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_scales_with_tile_size() {
+        let mut config = Config::default();
+        assert_eq!(config.window_size(), (800, 600));
+        config.tile_size = 40;
+        assert_eq!(config.window_size(), (1280, 960));
+        config.tile_size = 10;
+        assert_eq!(config.window_size(), (320, 240));
+    }
+
+    #[test]
+    fn zero_demo_idle_never_starts_the_demo() {
+        let mut config = Config::default();
+        assert_eq!(config.demo_idle_ms(), Some(30_000));
+        config.demo_idle_secs = 0;
+        assert_eq!(config.demo_idle_ms(), None);
+    }
+
+    #[test]
+    fn tile_size_out_of_range_is_an_error() {
+        let mut config = Config::default();
+        assert!(set_key(&mut config, "tile_size", "7").is_err());
+        assert!(set_key(&mut config, "tile_size", "65").is_err());
+        assert!(set_key(&mut config, "tile_size", "big").is_err());
+        assert!(set_key(&mut config, "tile_size", "32").is_ok());
+        assert_eq!(config.tile_size, 32);
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn framerate_defaults_when_not_given() {
+        assert_eq!(parse_framerate(&args(&["snake"]), 30), Ok(30));
+        assert_eq!(
+            parse_framerate(&args(&["snake", "--fps", "60"]), 30),
+            Ok(60)
+        );
+    }
+
+    #[test]
+    fn zero_or_bad_framerates_are_rejected() {
+        for bad in ["0", "fast", "-30", "", "241"].iter() {
+            let given = args(&["snake", "--fps", bad]);
+            assert!(parse_framerate(&given, 30).is_err(), "--fps {:?}", bad);
+        }
+        assert!(parse_framerate(&args(&["snake", "--fps"]), 30).is_err());
+
+        let mut config = Config::default();
+        assert!(set_key(&mut config, "framerate", "0").is_err());
+        assert!(set_key(&mut config, "framerate", "lots").is_err());
+        assert_eq!(config.framerate, 30);
+    }
+
+    #[test]
+    fn window_size_from_flags() {
+        let (w, h) = (TILE_W as u32, TILE_H as u32);
+        assert_eq!(
+            parse_window_size(&args(&["snake"]), (800, 600)),
+            Ok((800, 600))
+        );
+        let given = args(&["snake", "--width", &(w * 40).to_string()]);
+        assert_eq!(parse_window_size(&given, (800, 600)), Ok((w * 40, 600)));
+        let given = args(&["snake", "--height", &(h * 10).to_string()]);
+        assert_eq!(parse_window_size(&given, (800, 600)), Ok((800, h * 10)));
+    }
+
+    #[test]
+    fn zero_bad_or_part_tile_window_sizes_are_rejected() {
+        let part = (TILE_W as u32 + 1).to_string();
+        for bad in ["0", "wide", part.as_str()].iter() {
+            let given = args(&["snake", "--width", bad]);
+            assert!(
+                parse_window_size(&given, (800, 600)).is_err(),
+                "--width {:?}",
+                bad
+            );
+        }
+    }
+
+    /// a config file holding `text`
+    fn config_file(name: &str, text: &str) -> String {
+        let path = std::env::temp_dir().join(format!("rust_snake_test_{}.txt", name));
+        std::fs::write(&path, text).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn a_whole_config_file_is_read() {
+        let path = config_file(
+            "config_full",
+            "# every key there is\n\
+             width = 1000\n\
+             height = 500\n\
+             tile_size = 20\n\
+             framerate = 60   # smoother\n\
+             speed = 1.5\n\
+             min_interval_ms = 50\n\
+             start_length = 4\n\
+             \n\
+             wrap = true\n\
+             show_grid = true\n\
+             theme = \"dark\"\n\
+             follow_camera = true\n\
+             reduced_motion = true\n\
+             demo_idle_secs = 0\n\
+             smooth_movement = false\n\
+             turn_based = true\n",
+        );
+        let config = load_config(&path).unwrap();
+        assert_eq!((config.width, config.height), (1000, 500));
+        assert_eq!(config.tile_size, 20);
+        assert_eq!(config.framerate, 60);
+        assert_eq!(config.speed, 1.5);
+        assert_eq!(config.min_interval_ms, 50);
+        assert_eq!(config.start_length, 4);
+        assert_eq!(config.wrap, Some(true));
+        assert!(config.show_grid);
+        assert_eq!(config.theme, ThemeName::Dark);
+        assert!(config.follow_camera);
+        assert!(config.reduced_motion);
+        assert_eq!(config.demo_idle_ms(), None);
+        assert!(!config.smooth_movement);
+        assert!(config.turn_based);
+    }
+
+    #[test]
+    fn keys_left_out_keep_their_defaults() {
+        let config = load_config(&config_file("config_part", "speed = 2\n")).unwrap();
+        let default = Config::default();
+        assert_eq!(config.speed, 2.0);
+        assert_eq!(
+            (config.width, config.height),
+            (default.width, default.height)
+        );
+        assert_eq!(config.wrap, None);
+        assert!(config.smooth_movement);
+    }
+
+    #[test]
+    fn unknown_keys_and_bad_values_fail_the_file() {
+        let unknown = config_file("config_unknown", "width = 800\nlives = 9\n");
+        let err = load_config(&unknown).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(
+            err.to_string().ends_with(":2: unknown key \"lives\""),
+            "{}",
+            err
+        );
+
+        for (name, text) in [
+            ("config_bad_width", "width = 801\n"),
+            ("config_bad_bool", "wrap = yes\n"),
+            ("config_bad_theme", "theme = \"pink\"\n"),
+            ("config_bad_speed", "speed = -1\n"),
+            ("config_no_equals", "show_grid\n"),
+        ]
+        .iter()
+        {
+            let err = load_config(&config_file(name, text)).err();
+            assert!(err.is_some(), "{:?} loaded", text);
+        }
+    }
+
+    #[test]
+    fn the_shipped_config_is_the_defaults() {
+        let config = load_config("config.toml").unwrap();
+        let default = Config::default();
+        assert_eq!(
+            (config.width, config.height),
+            (default.width, default.height)
+        );
+        assert_eq!(config.framerate, default.framerate);
+        assert_eq!(config.smooth_movement, default.smooth_movement);
+        assert_eq!(config.turn_based, default.turn_based);
+    }
+
+    #[test]
+    fn a_missing_config_is_not_found() {
+        let err = load_config("no/such/config.toml").err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,152 @@
+//! the snake as it was over the last few ticks, so a death can be rewound
+
+use crate::snake::{Direction, Snake, Tail};
+use crate::{TILE_H, TILE_W};
+use std::collections::VecDeque;
+
+// -----------------------------------
+// HISTORY
+// -----------------------------------
+/// snake state captured at the start of a tick
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub head: (i32, i32),
+    pub dir: Direction,
+    pub tail: Vec<(i32, i32)>,
+}
+
+/// the last `len` frames, oldest first
+pub struct History {
+    frames: VecDeque<Frame>,
+    len: usize,
+}
+
+impl History {
+    pub fn new(len: usize) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(len),
+            len,
+        }
+    }
+
+    pub fn push(&mut self, snake: &Snake) {
+        if self.frames.len() == self.len {
+            self.frames.pop_front();
+        }
+
+        self.frames.push_back(Frame {
+            head: snake.head.cell(),
+            dir: snake.head.dir,
+            tail: snake.tail.iter().map(|t| t.cell()).collect(),
+        });
+    }
+
+    /// drop the last `ticks` frames and return the oldest one dropped,
+    /// or None if there is not enough history
+    pub fn rewind(&mut self, ticks: usize) -> Option<Frame> {
+        if ticks == 0 || self.frames.len() < ticks {
+            return None;
+        }
+
+        let start = self.frames.len() - ticks;
+        let frame = self.frames.remove(start);
+        self.frames.truncate(start);
+        frame
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// put `snake` back `ticks` ticks if a charge and enough history are left,
+/// spending the charge. false leaves everything as it was
+pub fn rewind(snake: &mut Snake, history: &mut History, charges: &mut u32, ticks: usize) -> bool {
+    if *charges == 0 {
+        return false;
+    }
+    let frame = match history.rewind(ticks) {
+        Some(frame) => frame,
+        None => return false,
+    };
+
+    snake.head.set_cell(frame.head.0, frame.head.1);
+    snake.head.set_direction(frame.dir);
+    snake.grow_pending = false;
+    snake.tail = frame
+        .tail
+        .iter()
+        .map(|&(x, y)| Tail::from_cell(x, y, (TILE_W, TILE_H)))
+        .collect();
+    *charges -= 1;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(snake: &Snake) -> Vec<(i32, i32)> {
+        let mut cells = vec![snake.head_tile()];
+        cells.extend(snake.tail.iter().map(|t| t.cell()));
+        cells
+    }
+
+    /// a snake moved `moves` ticks right, with each tick's start pushed
+    fn played(moves: usize) -> (Snake, History) {
+        let mut snake = Snake::with_length((2, 5), Direction::Right, 3);
+        let mut history = History::new(16);
+        for _ in 0..moves {
+            history.push(&snake);
+            snake.update(None);
+        }
+        (snake, history)
+    }
+
+    #[test]
+    fn rewind_restores_the_snake_and_spends_a_charge() {
+        let (before, _) = played(3);
+        let (mut snake, mut history) = played(6);
+        snake.head.set_direction(Direction::Down);
+        snake.grow();
+        let mut charges = 2;
+        assert!(rewind(&mut snake, &mut history, &mut charges, 3));
+        assert_eq!(cells(&snake), cells(&before));
+        assert_eq!(snake.head.dir, Direction::Right);
+        assert!(!snake.grow_pending);
+        assert_eq!(charges, 1);
+    }
+
+    #[test]
+    fn no_charges_no_rewind() {
+        let (mut snake, mut history) = played(6);
+        let died = cells(&snake);
+        let mut charges = 0;
+        assert!(!rewind(&mut snake, &mut history, &mut charges, 3));
+        assert_eq!(cells(&snake), died);
+    }
+
+    #[test]
+    fn not_enough_history_keeps_the_charge() {
+        let (mut snake, mut history) = played(2);
+        let mut charges = 1;
+        assert!(!rewind(&mut snake, &mut history, &mut charges, 3));
+        assert_eq!(charges, 1);
+    }
+
+    #[test]
+    fn history_keeps_the_last_len_frames() {
+        let mut history = History::new(2);
+        let mut snake = Snake::with_length((2, 5), Direction::Right, 1);
+        for _ in 0..5 {
+            history.push(&snake);
+            snake.update(None);
+        }
+        // heads at 5 and 6 are left, the oldest of them comes back
+        assert_eq!(history.rewind(2).map(|f| f.head), Some((5, 5)));
+        assert_eq!(history.rewind(1), None);
+    }
+}
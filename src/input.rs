@@ -0,0 +1,580 @@
+//! key bindings, gamepad directions and which actions are held down
+
+use sfml::window::Key;
+
+// -----------------------------------
+// ENUMS
+// -----------------------------------
+/// things a key can be bound to
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Pause,
+}
+
+impl Action {
+    /// action named in a controls file
+    pub fn from_name(name: &str) -> Option<Action> {
+        match name.to_ascii_lowercase().as_str() {
+            "up" => Some(Action::Up),
+            "down" => Some(Action::Down),
+            "left" => Some(Action::Left),
+            "right" => Some(Action::Right),
+            "pause" => Some(Action::Pause),
+            _ => None,
+        }
+    }
+
+    /// every action, in the order the remap screen asks for them
+    pub const ALL: [Action; ACTION_COUNT] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Pause,
+    ];
+}
+
+// -----------------------------------
+// KEY STATE
+// -----------------------------------
+/// number of `Action` variants
+const ACTION_COUNT: usize = 5;
+
+/// which actions are held down, one flag per action
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct KeyState {
+    down: [bool; ACTION_COUNT],
+}
+
+impl KeyState {
+    pub fn on_key_down(&mut self, action: Action) {
+        self.down[action as usize] = true;
+    }
+
+    pub fn on_key_up(&mut self, action: Action) {
+        self.down[action as usize] = false;
+    }
+
+    pub fn is_down(&self, action: Action) -> bool {
+        self.down[action as usize]
+    }
+}
+
+// -----------------------------------
+// IDLE TIMER
+// -----------------------------------
+/// how long since the player last did anything, counted only on the frames
+/// it's ticked on so it pauses off the menu
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct IdleTimer {
+    idle_ms: i32,
+}
+
+impl IdleTimer {
+    /// any input starts the wait over
+    pub fn reset(&mut self) {
+        self.idle_ms = 0;
+    }
+
+    /// count `frame_ms` more of idling, true on the frame it reaches
+    /// `limit_ms` and then not again until a reset. None never times out
+    pub fn tick(&mut self, frame_ms: i32, limit_ms: Option<i32>) -> bool {
+        let limit = match limit_ms {
+            Some(ms) => ms,
+            None => return false,
+        };
+        let was_idle = self.idle_ms >= limit;
+        self.idle_ms = self.idle_ms.saturating_add(frame_ms.max(0));
+        !was_idle && self.idle_ms >= limit
+    }
+}
+
+// -----------------------------------
+// BINDINGS
+// -----------------------------------
+/// keys handled directly by the game loop that actions can not use
+pub const RESERVED_KEYS: [Key; 20] = [
+    Key::Escape,
+    Key::F5,
+    Key::F9,
+    Key::Tab,
+    Key::T,
+    Key::F2,
+    Key::F3,
+    Key::F11,
+    Key::G,
+    Key::N,
+    Key::R,
+    Key::L,
+    Key::I,
+    Key::M,
+    Key::C,
+    Key::Return,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+];
+
+pub struct Bindings {
+    keys: Vec<(Action, Key)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        Self {
+            keys: vec![
+                (Action::Up, Key::W),
+                (Action::Down, Key::S),
+                (Action::Left, Key::A),
+                (Action::Right, Key::D),
+                (Action::Up, Key::Up),
+                (Action::Down, Key::Down),
+                (Action::Left, Key::Left),
+                (Action::Right, Key::Right),
+                (Action::Pause, Key::P),
+            ],
+        }
+    }
+
+    /// just the arrow keys, for a second player beside one on WASD
+    pub fn arrows() -> Self {
+        Self {
+            keys: vec![
+                (Action::Up, Key::Up),
+                (Action::Down, Key::Down),
+                (Action::Left, Key::Left),
+                (Action::Right, Key::Right),
+            ],
+        }
+    }
+
+    /// defaults with every action listed in the controls file at `path`
+    /// rebound, or just the defaults if there is no such file.
+    ///
+    /// one `action = key, key ...` per line, blank lines and lines
+    /// starting with # are skipped
+    pub fn from_file(path: &str) -> std::result::Result<Self, String> {
+        let mut bindings = Self::new();
+
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(bindings),
+            Err(e) => return Err(format!("{}: {}", path, e)),
+        };
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let keys = parts.next().unwrap_or("");
+
+            let action = Action::from_name(name)
+                .ok_or_else(|| format!("{}:{}: unknown action {:?}", path, i + 1, name))?;
+
+            bindings.keys.retain(|(a, _)| *a != action);
+            for key_name in keys.split(',').map(|k| k.trim()).filter(|k| !k.is_empty()) {
+                let key = key_from_name(key_name)
+                    .ok_or_else(|| format!("{}:{}: unknown key {:?}", path, i + 1, key_name))?;
+                bindings.keys.push((action, key));
+            }
+        }
+
+        Ok(bindings)
+    }
+
+    /// action bound to a key, if any
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.keys.iter().find(|(_, k)| *k == key).map(|(a, _)| *a)
+    }
+
+    /// bind a key to an action, refusing keys already in use elsewhere.
+    /// replaces every key the action had before
+    pub fn bind(&mut self, action: Action, key: Key) -> std::result::Result<(), String> {
+        if RESERVED_KEYS.contains(&key) {
+            return Err(format!("{:?} is reserved", key));
+        }
+        if let Some(other) = self.action(key) {
+            if other != action {
+                return Err(format!("{:?} is already bound to {:?}", key, other));
+            }
+        }
+
+        self.keys.retain(|(a, _)| *a != action);
+        self.keys.push((action, key));
+        Ok(())
+    }
+
+    /// the bindings as a controls file `from_file` reads back
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for &action in Action::ALL.iter() {
+            let keys: Vec<String> = self
+                .keys
+                .iter()
+                .filter(|(a, _)| *a == action)
+                .filter_map(|(_, k)| key_name(*k))
+                .collect();
+            text.push_str(&format!("{:?} = {}\n", action, keys.join(", ")).to_ascii_lowercase());
+        }
+        text
+    }
+
+    /// error listing every key bound to more than one action
+    /// or to a reserved key
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        let mut conflicts = vec![];
+
+        for (i, (action, key)) in self.keys.iter().enumerate() {
+            if RESERVED_KEYS.contains(key) {
+                conflicts.push(format!("{:?} uses reserved key {:?}", action, key));
+            }
+
+            // only report each clash once, from its first binding
+            if self.keys[..i].iter().any(|(_, k)| k == key) {
+                continue;
+            }
+            let actions: Vec<String> = self
+                .keys
+                .iter()
+                .filter(|(_, k)| k == key)
+                .map(|(a, _)| format!("{:?}", a))
+                .collect();
+            if actions.len() > 1 {
+                conflicts.push(format!("{:?} is bound to {}", key, actions.join(", ")));
+            }
+        }
+
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("key binding conflicts: {}", conflicts.join("; ")))
+        }
+    }
+}
+
+// -----------------------------------
+// REMAP
+// -----------------------------------
+/// the remap screen, asking for a key for each action in turn. the keys
+/// go into fresh bindings so a key can move from one action to another
+pub struct Remap {
+    bindings: Bindings,
+    next: usize,
+    /// why the last key was refused
+    pub message: Option<String>,
+}
+
+impl Default for Remap {
+    fn default() -> Self {
+        Self {
+            bindings: Bindings { keys: vec![] },
+            next: 0,
+            message: None,
+        }
+    }
+}
+
+impl Remap {
+    /// action waiting for a key
+    pub fn action(&self) -> Option<Action> {
+        Action::ALL.get(self.next).copied()
+    }
+
+    /// bind `key` to the waiting action, or say why not. the new bindings
+    /// once every action has a key
+    pub fn press(&mut self, key: Key) -> Option<Bindings> {
+        let action = self.action()?;
+        if key_name(key).is_none() {
+            self.message = Some(format!("{:?} can not be saved", key));
+            return None;
+        }
+        if let Err(e) = self.bindings.bind(action, key) {
+            self.message = Some(e);
+            return None;
+        }
+
+        self.message = None;
+        self.next += 1;
+        if self.action().is_none() {
+            Some(std::mem::take(&mut self.bindings))
+        } else {
+            None
+        }
+    }
+}
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// name a controls file knows `key` by, None for keys it can't name
+pub fn key_name(key: Key) -> Option<String> {
+    let name = format!("{:?}", key).to_ascii_lowercase();
+    if key_from_name(&name) == Some(key) {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// sfml key for a name in a controls file, letters, digits, arrows
+/// and a few common extras
+pub fn key_from_name(name: &str) -> Option<Key> {
+    let key = match name.to_ascii_lowercase().as_str() {
+        "a" => Key::A,
+        "b" => Key::B,
+        "c" => Key::C,
+        "d" => Key::D,
+        "e" => Key::E,
+        "f" => Key::F,
+        "g" => Key::G,
+        "h" => Key::H,
+        "i" => Key::I,
+        "j" => Key::J,
+        "k" => Key::K,
+        "l" => Key::L,
+        "m" => Key::M,
+        "n" => Key::N,
+        "o" => Key::O,
+        "p" => Key::P,
+        "q" => Key::Q,
+        "r" => Key::R,
+        "s" => Key::S,
+        "t" => Key::T,
+        "u" => Key::U,
+        "v" => Key::V,
+        "w" => Key::W,
+        "x" => Key::X,
+        "y" => Key::Y,
+        "z" => Key::Z,
+        "0" | "num0" => Key::Num0,
+        "1" | "num1" => Key::Num1,
+        "2" | "num2" => Key::Num2,
+        "3" | "num3" => Key::Num3,
+        "4" | "num4" => Key::Num4,
+        "5" | "num5" => Key::Num5,
+        "6" | "num6" => Key::Num6,
+        "7" | "num7" => Key::Num7,
+        "8" | "num8" => Key::Num8,
+        "9" | "num9" => Key::Num9,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "space" => Key::Space,
+        "tab" => Key::Tab,
+        "backspace" => Key::BackSpace,
+        "escape" => Key::Escape,
+        "return" | "enter" => Key::Return,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// direction a stick at x, y (-100..100, +y down) points in, the axis pushed
+/// furthest wins. None inside the `deadzone` so a resting stick does nothing
+pub fn stick_action(x: f32, y: f32, deadzone: f32) -> Option<Action> {
+    if x.abs() < deadzone && y.abs() < deadzone {
+        return None;
+    }
+
+    if x.abs() > y.abs() {
+        if x > 0.0 {
+            Some(Action::Right)
+        } else {
+            Some(Action::Left)
+        }
+    } else if y > 0.0 {
+        Some(Action::Down)
+    } else {
+        Some(Action::Up)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// bindings read from a controls file holding `text`
+    fn controls(name: &str, text: &str) -> Bindings {
+        let path = std::env::temp_dir().join(format!("rust_snake_test_{}.txt", name));
+        std::fs::write(&path, text).unwrap();
+        Bindings::from_file(&path.to_string_lossy()).unwrap()
+    }
+
+    #[test]
+    fn keys_are_down_until_released() {
+        let mut keys = KeyState::default();
+        assert!(!keys.is_down(Action::Up));
+        keys.on_key_down(Action::Up);
+        keys.on_key_down(Action::Left);
+        assert!(keys.is_down(Action::Up) && keys.is_down(Action::Left));
+        assert!(!keys.is_down(Action::Down));
+
+        keys.on_key_up(Action::Up);
+        assert!(!keys.is_down(Action::Up));
+        assert!(keys.is_down(Action::Left));
+        // releasing a key that isn't down changes nothing
+        keys.on_key_up(Action::Pause);
+        assert!(!keys.is_down(Action::Pause));
+    }
+
+    #[test]
+    fn every_action_has_its_own_flag() {
+        let actions = [
+            Action::Up,
+            Action::Down,
+            Action::Left,
+            Action::Right,
+            Action::Pause,
+        ];
+        assert_eq!(actions.len(), ACTION_COUNT);
+        for &action in actions.iter() {
+            let mut keys = KeyState::default();
+            keys.on_key_down(action);
+            for &other in actions.iter() {
+                assert_eq!(keys.is_down(other), other == action);
+            }
+        }
+    }
+
+    #[test]
+    fn arrows_and_wasd_both_steer() {
+        let bindings = Bindings::new();
+        assert_eq!(bindings.action(Key::Up), Some(Action::Up));
+        assert_eq!(bindings.action(Key::W), Some(Action::Up));
+        assert_eq!(bindings.action(Key::Left), Some(Action::Left));
+        assert_eq!(bindings.action(Key::A), Some(Action::Left));
+    }
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        assert_eq!(Bindings::new().validate(), Ok(()));
+    }
+
+    #[test]
+    fn a_key_on_two_actions_is_a_conflict() {
+        let bindings = controls("duplicate", "up = w\ndown = w, s\n");
+        let err = bindings.validate().unwrap_err();
+        assert!(err.contains("W is bound to Up, Down"), "{}", err);
+    }
+
+    #[test]
+    fn a_reserved_key_is_a_conflict() {
+        let bindings = controls("reserved", "pause = escape\n");
+        let err = bindings.validate().unwrap_err();
+        assert!(err.contains("Pause uses reserved key Escape"), "{}", err);
+    }
+
+    #[test]
+    fn bind_refuses_keys_in_use() {
+        let mut bindings = Bindings::new();
+        assert!(bindings.bind(Action::Up, Key::S).is_err());
+        assert!(bindings.bind(Action::Up, Key::R).is_err());
+        assert_eq!(
+            bindings.bind(Action::Up, Key::I),
+            Err("I is reserved".to_string())
+        );
+        assert_eq!(bindings.bind(Action::Up, Key::K), Ok(()));
+        assert_eq!(bindings.action(Key::K), Some(Action::Up));
+        assert_eq!(bindings.action(Key::W), None);
+    }
+
+    #[test]
+    fn remap_asks_for_every_action_in_turn() {
+        let mut remap = Remap::default();
+        assert_eq!(remap.action(), Some(Action::Up));
+        // S was Down's, free again in the new bindings
+        assert!(remap.press(Key::S).is_none());
+        assert_eq!(remap.action(), Some(Action::Down));
+        assert!(remap.press(Key::X).is_none());
+        assert!(remap.press(Key::Left).is_none());
+        assert!(remap.press(Key::Right).is_none());
+        let bindings = remap.press(Key::Space).unwrap();
+
+        assert_eq!(remap.action(), None);
+        assert_eq!(bindings.action(Key::S), Some(Action::Up));
+        assert_eq!(bindings.action(Key::Space), Some(Action::Pause));
+        assert_eq!(bindings.action(Key::W), None);
+        assert_eq!(bindings.validate(), Ok(()));
+    }
+
+    #[test]
+    fn remap_refuses_and_says_why() {
+        let mut remap = Remap::default();
+        assert!(remap.press(Key::R).is_none());
+        assert_eq!(remap.message, Some("R is reserved".to_string()));
+        assert_eq!(remap.action(), Some(Action::Up));
+
+        remap.press(Key::K);
+        assert!(remap.press(Key::K).is_none());
+        assert_eq!(remap.message, Some("K is already bound to Up".to_string()));
+        assert_eq!(remap.action(), Some(Action::Down));
+
+        // nothing to write it down as in the controls file
+        assert!(remap.press(Key::F1).is_none());
+        assert_eq!(remap.action(), Some(Action::Down));
+    }
+
+    #[test]
+    fn bindings_read_back_from_their_text() {
+        let bindings = Bindings::new();
+        let text = bindings.to_text();
+        assert!(text.starts_with("up = w, up\n"), "{}", text);
+
+        let read = controls("to_text", &text);
+        for &key in [Key::W, Key::Up, Key::S, Key::A, Key::Right, Key::P].iter() {
+            assert_eq!(read.action(key), bindings.action(key));
+        }
+        assert_eq!(read.keys.len(), bindings.keys.len());
+    }
+
+    #[test]
+    fn idle_times_out_at_the_limit() {
+        let mut idle = IdleTimer::default();
+        assert!(!idle.tick(400, Some(1000)));
+        assert!(!idle.tick(599, Some(1000)));
+        assert!(idle.tick(1, Some(1000)));
+    }
+
+    #[test]
+    fn idle_times_out_once() {
+        let mut idle = IdleTimer::default();
+        assert!(idle.tick(1500, Some(1000)));
+        assert!(!idle.tick(16, Some(1000)));
+        assert!(!idle.tick(5000, Some(1000)));
+    }
+
+    #[test]
+    fn input_restarts_the_wait() {
+        let mut idle = IdleTimer::default();
+        assert!(!idle.tick(900, Some(1000)));
+        idle.reset();
+        assert!(!idle.tick(900, Some(1000)));
+        assert!(idle.tick(100, Some(1000)));
+        // and can time out again after the demo ends
+        idle.reset();
+        assert!(idle.tick(1000, Some(1000)));
+    }
+
+    #[test]
+    fn no_limit_never_times_out() {
+        let mut idle = IdleTimer::default();
+        assert!(!idle.tick(i32::MAX, None));
+        assert!(!idle.tick(i32::MAX, None));
+    }
+}
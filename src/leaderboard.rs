@@ -0,0 +1,119 @@
+//! the top scores and the names they were set under
+
+use std::fs::File;
+use std::io::{Result, Write};
+
+// -----------------------------------
+// CONSTS
+// -----------------------------------
+/// most entries kept, the lowest drops off when a new one beats it
+pub const LEADERBOARD_LEN: usize = 10;
+/// letters in a leaderboard name
+pub const NAME_LEN: usize = 3;
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// would `score` make it onto the board
+pub fn qualifies(entries: &[(String, u32)], score: u32) -> bool {
+    score > 0
+        && (entries.len() < LEADERBOARD_LEN
+            || entries.last().is_none_or(|&(_, lowest)| score > lowest))
+}
+
+/// add an entry keeping the board sorted high to low and capped at
+/// LEADERBOARD_LEN, ties go below the scores already there
+pub fn insert_score(entries: &mut Vec<(String, u32)>, name: &str, score: u32) {
+    let at = entries
+        .iter()
+        .position(|&(_, s)| score > s)
+        .unwrap_or(entries.len());
+    entries.insert(at, (name.to_string(), score));
+    entries.truncate(LEADERBOARD_LEN);
+}
+
+/// `name score` per line, an empty board if missing, bad lines are skipped
+pub fn load_leaderboard(path: &str) -> Vec<(String, u32)> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(_) => return vec![],
+    };
+
+    let mut entries = vec![];
+    for line in text.lines() {
+        let mut parts = line.split_whitespace();
+        if let (Some(name), Some(Ok(score))) = (parts.next(), parts.next().map(str::parse)) {
+            insert_score(&mut entries, name, score);
+        }
+    }
+    entries
+}
+
+pub fn save_leaderboard(path: &str, entries: &[(String, u32)]) -> Result<()> {
+    let mut file = File::create(path)?;
+    for (name, score) in entries.iter() {
+        writeln!(file, "{} {}", name, score)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full_board() -> Vec<(String, u32)> {
+        let mut entries = vec![];
+        for i in 0..LEADERBOARD_LEN as u32 {
+            insert_score(&mut entries, "AAA", (i + 1) * 10);
+        }
+        entries
+    }
+
+    #[test]
+    fn inserts_high_to_low() {
+        let mut entries = vec![];
+        insert_score(&mut entries, "BOB", 20);
+        insert_score(&mut entries, "ANN", 50);
+        insert_score(&mut entries, "CAL", 30);
+        let scores: Vec<u32> = entries.iter().map(|e| e.1).collect();
+        assert_eq!(scores, vec![50, 30, 20]);
+    }
+
+    #[test]
+    fn ties_go_below() {
+        let mut entries = vec![];
+        insert_score(&mut entries, "OLD", 40);
+        insert_score(&mut entries, "NEW", 40);
+        assert_eq!(entries[0].0, "OLD");
+        assert_eq!(entries[1].0, "NEW");
+    }
+
+    #[test]
+    fn truncates_to_the_board_length() {
+        let mut entries = full_board();
+        insert_score(&mut entries, "TOP", 500);
+        assert_eq!(entries.len(), LEADERBOARD_LEN);
+        assert_eq!(entries[0], ("TOP".to_string(), 500));
+        assert_eq!(entries.last().unwrap().1, 20);
+    }
+
+    #[test]
+    fn qualifying_scores() {
+        assert!(!qualifies(&[], 0));
+        assert!(qualifies(&[], 1));
+        let entries = full_board();
+        assert!(!qualifies(&entries, 10));
+        assert!(qualifies(&entries, 11));
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let path = std::env::temp_dir().join("rust_snake_test_leaderboard.txt");
+        let path = path.to_str().unwrap();
+        let entries = full_board();
+        save_leaderboard(path, &entries).unwrap();
+        assert_eq!(load_leaderboard(path), entries);
+        let _ = std::fs::remove_file(path);
+        assert!(load_leaderboard(path).is_empty());
+    }
+}
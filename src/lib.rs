@@ -0,0 +1,48 @@
+//! RUST SNAKE
+//!
+//! game pieces shared by the binary, split out so they can be tested
+
+pub mod ai;
+pub mod config;
+pub mod history;
+pub mod input;
+pub mod leaderboard;
+pub mod map;
+pub mod obstacle;
+pub mod particle;
+pub mod render;
+pub mod replay;
+pub mod save;
+pub mod sim;
+pub mod snake;
+pub mod spawn;
+pub mod state;
+pub mod stats;
+pub mod theme;
+pub mod timeline;
+pub mod timing;
+
+// -----------------------------------
+// CONSTS
+// -----------------------------------
+/// px size of a tile across and down, they can differ for wider boards
+pub const TILE_W: f32 = 25.0;
+pub const TILE_H: f32 = 25.0;
+pub const MAP_PATH: &str = "assets/map/data.txt";
+/// where the map select screen looks for maps
+pub const MAP_DIR: &str = "assets/map";
+
+/// fraction of a tile the snake segments fill, < 1.0 leaves gaps between them
+pub const SEGMENT_SCALE: f32 = 1.0;
+/// how far (0..100) a gamepad stick must be pushed before it counts
+pub const JOYSTICK_DEADZONE: f32 = 35.0;
+/// size of the head's eyes as a fraction of a tile
+pub const EYE_SCALE: f32 = 0.18;
+/// most direction changes buffered between moves
+pub const INPUT_QUEUE_LEN: usize = 3;
+
+/// highest frame limit the config or --fps can ask for
+pub const MAX_FRAMERATE: u32 = 240;
+
+/// attempts at a connected obstacle layout before giving up
+pub const SHUFFLE_ATTEMPTS: usize = 50;
@@ -1,558 +1,2262 @@
 //! RUST SNAKE
 
 // IMPORTS
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_sfml_snake::{
+    ai::*, config::*, history::*, input::*, leaderboard::*, map::*, obstacle::*, particle::*,
+    render::*, replay::*, save::*, sim::*, snake::*, spawn::*, state::*, stats::*, theme::*,
+    timeline::*, timing::*, JOYSTICK_DEADZONE, MAP_DIR, MAP_PATH, TILE_H, TILE_W,
+};
+use sfml::audio::{Music, Sound, SoundBuffer};
+use sfml::window::joystick::{self, Axis};
 use sfml::{graphics::*, system::*, window::*};
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader, Result};
+use std::error::Error;
+use std::io::ErrorKind;
+use std::path::Path;
 
 // -----------------------------------
 // CONSTS
 // -----------------------------------
-const BLOCK_SIZE: f32 = 25.0;
-const SCREEN_WIDTH: u32 = 800;
-const SCREEN_HEIGHT: u32 = 600;
+/// (col, row) grid cell the snake starts from
+const START_CELL: (i32, i32) = (6, 6);
+const FONT_PATH: &str = "assets/font/sansation.ttf";
+/// optional window icon
+const ICON_PATH: &str = "assets/icon.png";
+const WINDOW_TITLE: &str = "rust_snake";
+const EAT_SFX_PATH: &str = "assets/sfx/eat.ogg";
+const DEATH_SFX_PATH: &str = "assets/sfx/death.ogg";
+const MUSIC_PATH: &str = "assets/music.ogg";
+/// optional key remaps, see Bindings::from_file
+const CONTROLS_PATH: &str = "assets/controls.txt";
+/// where the last finished game's replay is written
+const REPLAY_PATH: &str = "replay.txt";
+/// F5 saves the game in progress here, F9 loads it back
+const SAVE_PATH: &str = "assets/savegame.txt";
+const HIGH_SCORE_PATH: &str = "assets/highscore.txt";
+/// top LEADERBOARD_LEN named scores
+const SCORES_PATH: &str = "assets/scores.txt";
+/// totals across every game played
+const STATS_PATH: &str = "assets/stats.txt";
+/// optional object layer drawn over MAP_PATH
+const OBJECTS_PATH: &str = "assets/map/objects.txt";
+/// chance (0..1) of each inner tile of a random map being a wall
+const RANDOM_WALL_DENSITY: f32 = 0.08;
+/// gamepad read for directions
+const JOYSTICK_ID: u32 = 0;
+/// window size, speed and the like, the defaults are used without it
+const CONFIG_PATH: &str = "config.toml";
+
+/// how many food tiles are on the map at once
+const FOOD_COUNT: usize = 1;
+
+/// chance (0..1) of a bonus tile appearing each time food is eaten
+const BONUS_CHANCE: f32 = 0.2;
+/// moves a bonus tile stays on the map for before it vanishes, gameplay
+/// timing is all in moves so a replay plays out the same at any frame rate
+const BONUS_LIFETIME_TICKS: usize = 50;
+/// score for eating a bonus tile in time
+const BONUS_SCORE: u32 = 5;
+
+/// chance (0..1) of a shrink tile appearing each time food is eaten
+const SHRINK_CHANCE: f32 = 0.1;
+/// most tail segments a shrink tile takes off
+const SHRINK_AMOUNT: usize = 3;
+/// score for eating a shrink tile
+const SHRINK_SCORE: u32 = 2;
+/// chance (0..1) of a boost tile appearing each time food is eaten
+const BOOST_CHANCE: f32 = 0.1;
+/// moves a boost lasts once eaten
+const BOOST_TICKS: usize = 40;
+/// move interval while boosted as a fraction of the normal one
+const BOOST_SCALE: f32 = 0.6;
+/// food eaten within this many moves of the last raises the score multiplier
+const COMBO_WINDOW_TICKS: usize = 30;
+const MAX_MULTIPLIER: u32 = 5;
+
+/// how many poison tiles are kept on the map alongside food (difficulty)
+const POISON_TILES: usize = 2;
+/// what happens when the head lands on a poison tile
+const POISON_PENALTY: PoisonPenalty = PoisonPenalty::Shrink(5);
+
+/// where food and poison prefer to spawn
+const SPAWN_WEIGHTING: SpawnWeighting = SpawnWeighting::Uniform;
+
+/// ms of 3-2-1 before the snake moves, at the start and after a respawn
+const COUNTDOWN_MS: i32 = 3_000;
+/// ms "Go!" stays up once the snake is moving
+const GO_MS: i32 = 500;
+
+/// moves a timed game starts with, the hud shows them as time at the
+/// current speed
+const TIMED_MODE_TICKS: usize = 600;
+/// moves added in a timed game for each food eaten
+const TIMED_FOOD_BONUS_TICKS: usize = 30;
+
+/// lives per game, a death with no rewind left costs one
+const LIVES: u32 = 3;
+/// ticks after a respawn during which nothing can kill the snake
+const INVULNERABLE_TICKS: u32 = 15;
+/// how far (0..1) toward white an invulnerable snake is drawn with reduced
+/// motion, in place of blinking
+const INVULNERABLE_TINT: f32 = 0.5;
+
+/// rewinds available per run
+const REWIND_CHARGES: u32 = 3;
+/// how many ticks a rewind steps back
+const REWIND_TICKS: usize = 3;
+/// how many ticks of history are kept
+const HISTORY_LEN: usize = 16;
+
+/// where the --trace timeline is written
+const TIMELINE_PATH: &str = "timeline.txt";
+
+/// pulse the head as the score closes in on the high score
+const WARP_FINALE: bool = true;
+/// how close (in points) to the high score the pulse starts
+const WARP_MARGIN: u32 = 5;
+
+/// leave a slime trail that slows the snake when it crosses it
+const SLIME_TRAIL: bool = false;
+/// ticks a vacated tile stays slimed
+const SLIME_TICKS: u64 = 20;
+/// ticks the snake stays slowed after touching slime
+const SLIME_SLOW_TICKS: u32 = 10;
+/// extra ms between snake moves while slowed
+const SLIME_SLOW_MS: i32 = 55;
+
+/// assist: steer along a wall instead of hitting it when only one way is open
+const AUTO_TURN_ASSIST: bool = false;
+
+/// move obstacle tiles to new random spots at the start of each run
+const SHUFFLE_OBSTACLES: bool = false;
+
+/// wall off a random open tile every WALL_EVERY_FOOD food eaten,
+/// tightening the board as the game goes on
+const GROWING_WALLS: bool = false;
+const WALL_EVERY_FOOD: u32 = 5;
+/// most walls added in one game
+const MAX_ADDED_WALLS: usize = 20;
+
+/// hazards patrolling the board, 0 for none
+const MOVING_OBSTACLES: usize = 0;
+
+/// clear walls and obstacles around the spawn point when a map is loaded
+const SAFE_START_ZONE: bool = false;
+/// how many tiles around the spawn point get cleared
+const SAFE_START_RADIUS: i32 = 2;
+
+/// how long (ms) frames are counted for each fps reading
+const FPS_SAMPLE_MS: i32 = 500;
+
+/// most moves made in one frame catching up, the rest is dropped
+const MAX_MOVES_PER_FRAME: u32 = 4;
+
+/// how long (ms) the screen shakes after a crash and how far (px) at first
+const SHAKE_MS: i32 = 300;
+const SHAKE_PX: f32 = 6.0;
+/// how long (ms) the snake flashes after growing and how far (0..1) toward white
+const FLASH_MS: i32 = 100;
+const FLASH_AMOUNT: f32 = 0.6;
+/// px width of the minimap in the corner when the camera follows the head
+const MINIMAP_WIDTH: f32 = 160.0;
 
 // -----------------------------------
 // ENUMS
 // -----------------------------------
-#[derive(PartialEq)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+/// rules for a game
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Mode {
+    /// play until out of lives
+    Classic,
+    /// score as much as possible before the clock runs out
+    Timed,
+    /// two players on one keyboard, the first to crash loses
+    Versus,
+    /// versus with player two played by the computer
+    VsComputer,
+    /// attract screen, the snake plays itself and scores aren't kept
+    Demo,
+    /// one life, no rewinds, and dying deletes its save
+    Hardcore,
 }
 
-#[derive(PartialEq, Clone)]
-enum TileType {
-    Blocked,
-    NonBlocked,
-    Active,
-    NonActive,
-}
-
-// -----------------------------------
-// HEAD
-// -----------------------------------
-#[allow(dead_code)]
-struct Head<'a> {
-    position: Vector2f,
-    scale: f32,
-    is_active: bool,
-    dir: Direction,
-    rect_shape: RectangleShape<'a>,
-}
-
-impl<'a> Head<'a> {
-    fn new(x: f32, y: f32, scale: f32, color: Color, dir: Direction) -> Self {
-        let mut r = RectangleShape::new();
-        r.set_size((scale, scale));
-        r.set_fill_color(color);
-        r.set_position((x, y));
-        r.set_origin((0.0, 0.0));
-
-        Self {
-            position: Vector2f::new(x, y),
-            scale: scale,
-            is_active: true,
-            dir: dir,
-            rect_shape: r,
+impl Mode {
+    /// (lives, rewind charges) a game starts with
+    fn allowance(self) -> (u32, u32) {
+        match self {
+            Mode::Hardcore => (1, 0),
+            _ => (LIVES, REWIND_CHARGES),
         }
     }
 
-    fn reset(&mut self, x: f32, y: f32) {
-        self.set_pos(x, y);
-        self.set_direction(Direction::Right);
-    }
-
-    fn set_pos(&mut self, x: f32, y: f32) {
-        self.rect_shape.set_position((x, y));
-    }
-
-    fn set_direction(&mut self, new_dir: Direction) {
-        self.dir = new_dir;
-    }
-
-    /// get x screen position
-    fn get_x(&self) -> f32 {
-        self.rect_shape.position().x
-    }
-
-    /// get y screen position
-    fn get_y(&self) -> f32 {
-        self.rect_shape.position().y
+    /// the mode `{:?}` prints as `name`
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Classic" => Some(Mode::Classic),
+            "Timed" => Some(Mode::Timed),
+            "Versus" => Some(Mode::Versus),
+            "VsComputer" => Some(Mode::VsComputer),
+            "Demo" => Some(Mode::Demo),
+            "Hardcore" => Some(Mode::Hardcore),
+            _ => None,
+        }
     }
 
-    fn draw(&mut self, win: &mut RenderWindow) {
-        if !self.is_active {
-            return;
+    fn next(self) -> Self {
+        match self {
+            Mode::Classic => Mode::Timed,
+            Mode::Timed => Mode::Hardcore,
+            Mode::Hardcore => Mode::Versus,
+            Mode::Versus => Mode::VsComputer,
+            Mode::VsComputer => Mode::Demo,
+            Mode::Demo => Mode::Classic,
         }
-
-        win.draw(&self.rect_shape);
     }
+}
 
-    fn inputs(&mut self, input_map: &HashMap<&Key, bool>) {
-        if !self.is_active {
-            return;
-        }
+/// how a versus game ended
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Winner {
+    PlayerOne,
+    PlayerTwo,
+    Draw,
+}
 
-        if input_map[&Key::W] && self.dir != Direction::Down {
-            self.dir = Direction::Up;
-            return;
+impl Winner {
+    fn from_deaths(one_died: bool, two_died: bool) -> Self {
+        match (one_died, two_died) {
+            (true, false) => Winner::PlayerTwo,
+            (false, true) => Winner::PlayerOne,
+            _ => Winner::Draw,
         }
+    }
+}
 
-        if input_map[&Key::S] && self.dir != Direction::Up {
-            self.dir = Direction::Down;
-            return;
-        }
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Difficulty {
+    /// below easy, for players who need more time to react
+    Slow,
+    Easy,
+    Normal,
+    Hard,
+}
 
-        if input_map[&Key::A] && self.dir != Direction::Right {
-            self.dir = Direction::Left;
-            return;
-        }
+/// gameplay knobs set by a difficulty preset
+struct DifficultySettings {
+    /// ms between snake moves at the start of a run
+    base_interval_ms: i32,
+    /// ms taken off the move interval per tail segment
+    ramp_ms_per_segment: i32,
+    /// leaving one edge of the map comes back in on the opposite edge
+    wrap: bool,
+    /// running into a wall or obstacle ends the run
+    walls_lethal: bool,
+}
 
-        if input_map[&Key::D] && self.dir != Direction::Left {
-            self.dir = Direction::Right;
-            return;
+impl Difficulty {
+    /// the difficulty `{:?}` prints as `name`
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Slow" => Some(Difficulty::Slow),
+            "Easy" => Some(Difficulty::Easy),
+            "Normal" => Some(Difficulty::Normal),
+            "Hard" => Some(Difficulty::Hard),
+            _ => None,
         }
     }
 
-    fn update(&mut self) {
-        if !self.is_active {
-            return;
+    fn settings(&self) -> DifficultySettings {
+        match self {
+            Difficulty::Slow => DifficultySettings {
+                base_interval_ms: 180,
+                ramp_ms_per_segment: 0,
+                wrap: true,
+                walls_lethal: false,
+            },
+            Difficulty::Easy => DifficultySettings {
+                base_interval_ms: 130,
+                ramp_ms_per_segment: 1,
+                wrap: true,
+                walls_lethal: false,
+            },
+            Difficulty::Normal => DifficultySettings {
+                base_interval_ms: 95,
+                ramp_ms_per_segment: 2,
+                wrap: false,
+                walls_lethal: true,
+            },
+            Difficulty::Hard => DifficultySettings {
+                base_interval_ms: 70,
+                ramp_ms_per_segment: 3,
+                wrap: false,
+                walls_lethal: true,
+            },
         }
+    }
+}
 
-        let new_dir = match self.dir {
-            Direction::Up => Vector2f::new(0.0, -1.0),
-            Direction::Down => Vector2f::new(0.0, 1.0),
-            Direction::Left => Vector2f::new(-1.0, 0.0),
-            Direction::Right => Vector2f::new(1.0, 0.0),
-        };
+/// where a playable map comes from
+#[derive(Clone)]
+enum MapSource {
+    /// a map file in MAP_DIR
+    File(String),
+    /// walls round the edge and nothing else, used when MAP_DIR has no maps
+    Blank,
+    /// generated from the run rng, so the same seed gives the same map
+    Random,
+}
 
-        self.rect_shape.move_(new_dir * self.scale);
+impl MapSource {
+    /// name shown on the map select screen
+    fn name(&self) -> String {
+        match self {
+            MapSource::File(path) => Path::new(path)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone()),
+            MapSource::Blank => "Blank".to_string(),
+            MapSource::Random => "Random".to_string(),
+        }
     }
 }
 
 // -----------------------------------
-// TAIL
+// GAME
 // -----------------------------------
-#[allow(dead_code)]
-struct Tail<'a> {
-    position: Vector2f,
-    scale: f32,
-    is_active: bool,
-    rect_shape: RectangleShape<'a>,
-}
-
-impl<'a> Tail<'a> {
-    fn new(x: f32, y: f32, scale: f32, color: Color) -> Self {
-        let mut r = RectangleShape::new();
-        r.set_size((scale, scale));
-        r.set_fill_color(color);
-        r.set_position((x, y));
-        r.set_origin((0.0, 0.0));
+/// where games on the chosen map start from
+struct Level {
+    /// the map's name, as the map select screen shows it
+    name: String,
+    /// seed the map's random walls were made from
+    map_seed: u64,
+    spawn: (i32, i32),
+    rival_spawn: (i32, i32),
+    /// tiles each snake starts with
+    length: usize,
+    /// food placed in the map file counts towards FOOD_COUNT
+    placed_food: Vec<(i32, i32)>,
+}
+
+/// everything a single game keeps track of, put back by start_game
+struct Game {
+    snake: Snake,
+    /// player two, versus mode only
+    rival: Option<Snake>,
+    food: Vec<(i32, i32)>,
+    history: History,
+    lives: u32,
+    rewind_charges: u32,
+    invulnerable_ticks: u32,
+    score: u32,
+    rival_score: u32,
+    winner: Option<Winner>,
+    /// only offer the leaderboard once per game
+    score_entered: bool,
+    slow_ticks: u32,
+    /// cell of the bonus tile on the map, if any, and the tick it went up on
+    bonus: Option<(i32, i32)>,
+    bonus_tick: usize,
+    /// GROWING_WALLS: food eaten this game and the walls it put up
+    food_eaten: u32,
+    added_walls: Vec<(i32, i32)>,
+    /// combo: score gains are multiplied, last_eat_tick is when food was
+    /// last eaten
+    multiplier: u32,
+    last_eat_tick: usize,
+    /// the snake moves faster while `tick` is short of boost_until
+    boost_until: usize,
+    /// time spent playing this game, pauses, countdowns and menus left out
+    played_ms: i32,
+    /// most tiles the snake has covered this game
+    longest: usize,
+    obstacles: Vec<Obstacle>,
+    /// moves of the game being played, saved to REPLAY_PATH when it ends
+    recording: Option<Replay>,
+    /// moves made this game, indexes the replay being recorded or played
+    /// and times everything else that runs out
+    tick: usize,
+    /// what the player's last move ran into, acted on at the top of the next frame
+    last_step: StepOutcome,
+    /// the same for player two
+    rival_step: StepOutcome,
+    /// timed mode: the tick the game ends on
+    time_limit: usize,
+    countdown: Clock,
+}
 
+impl Game {
+    /// a game of `mode` with the snakes on the level's spawns and nothing
+    /// else placed yet
+    fn new(mode: Mode, level: &Level) -> Self {
+        let (lives, rewind_charges) = mode.allowance();
+        let rival = if mode == Mode::Versus || mode == Mode::VsComputer {
+            Some(Snake::with_length(
+                level.rival_spawn,
+                Direction::Left,
+                level.length,
+            ))
+        } else {
+            None
+        };
         Self {
-            position: Vector2f::new(x, y),
-            scale: scale,
-            is_active: true,
-            rect_shape: r,
+            snake: Snake::with_length(level.spawn, Direction::Right, level.length),
+            rival,
+            food: vec![],
+            history: History::new(HISTORY_LEN),
+            lives,
+            rewind_charges,
+            invulnerable_ticks: 0,
+            score: 0,
+            rival_score: 0,
+            winner: None,
+            score_entered: false,
+            slow_ticks: 0,
+            bonus: None,
+            bonus_tick: 0,
+            food_eaten: 0,
+            added_walls: vec![],
+            multiplier: 1,
+            last_eat_tick: 0,
+            boost_until: 0,
+            played_ms: 0,
+            longest: 0,
+            obstacles: vec![],
+            recording: None,
+            tick: 0,
+            last_step: StepOutcome::default(),
+            rival_step: StepOutcome::default(),
+            time_limit: TIMED_MODE_TICKS,
+            countdown: Clock::start(),
         }
     }
+}
 
-    /// get x screen position
-    fn get_x(&self) -> f32 {
-        self.rect_shape.position().x
-    }
-
-    /// get y screen position
-    fn get_y(&self) -> f32 {
-        self.rect_shape.position().y
+/// start `game` over as a new game of `mode` on `map`, the one place the
+/// menu, a restart and a loaded save all begin from. walls the last game put
+/// up come down and the items are placed from a fresh seed, or from the
+/// replay's seed when one is being played back, which isn't recorded.
+/// `difficulty` and `wrap` only go in the recording's header
+#[allow(clippy::too_many_arguments)]
+fn start_game(
+    game: &mut Game,
+    mode: Mode,
+    difficulty: Difficulty,
+    wrap: bool,
+    level: &Level,
+    map: &mut Map,
+    rng: &mut StdRng,
+    playback_seed: Option<u64>,
+    music: Option<&mut Music>,
+) {
+    for (x, y) in game.added_walls.drain(..) {
+        map.deactivate_tile(x, y);
     }
-
-    fn draw(&mut self, win: &mut RenderWindow) {
-        if !self.is_active {
-            return;
-        }
-
-        win.draw(&self.rect_shape);
+    // every game starts from its own seed so it can be replayed
+    let seed = playback_seed.unwrap_or_else(|| rng.gen());
+    *rng = StdRng::seed_from_u64(seed);
+    *game = Game::new(mode, level);
+
+    let (rows, cols) = (map.width, map.height);
+    let snakes = all_snakes(&game.snake, &game.rival);
+    reset_items(
+        rng,
+        rows,
+        cols,
+        &snakes,
+        &level.placed_food,
+        map,
+        &mut game.food,
+    );
+    game.obstacles = spawn_obstacles(rng, rows, cols, &snakes, map);
+    if playback_seed.is_none() {
+        let header = ReplayHeader {
+            map: level.name.clone(),
+            map_seed: level.map_seed,
+            size: (map.width, map.height),
+            mode: format!("{:?}", mode),
+            difficulty: format!("{:?}", difficulty),
+            start_length: level.length,
+            wrap,
+        };
+        game.recording = Some(Replay::new(seed, header));
     }
-
-    fn update(&mut self, px: f32, py: f32) {
-        if !self.is_active {
-            return;
-        }
-        // get direction to take
-        // rect in front previous screen position - this rect's current screen position
-        // (-1, 0) or (1, 1) ...
-        let nd = Vector2f::new(px, py) - self.rect_shape.position();
-
-        self.rect_shape.move_(nd);
+    if let Some(m) = music {
+        m.play();
     }
 }
 
 // -----------------------------------
-// TILE
+// FUNCS
 // -----------------------------------
-#[derive(Clone)]
-struct Tile<'a> {
-    rect: RectangleShape<'a>,
-    scale: f32,
-    tile_type: TileType,
+/// every snake on the map, spawns keep off all of them
+fn all_snakes<'s>(snake: &'s Snake, rival: &'s Option<Snake>) -> Vec<&'s Snake> {
+    let mut snakes = vec![snake];
+    snakes.extend(rival.iter());
+    snakes
 }
 
-impl<'a> Tile<'a> {
-    fn new(scale: f32, tile: TileType) -> Self {
-        Self {
-            rect: RectangleShape::new(),
-            scale: scale,
-            tile_type: tile,
+/// top `food` and the map back up to FOOD_COUNT food tiles, or as many as
+/// there is room for
+fn spawn_food(
+    rng: &mut StdRng,
+    rows: i32,
+    cols: i32,
+    snakes: &[&Snake],
+    map_data: &mut Map,
+    food: &mut Vec<(i32, i32)>,
+) {
+    while food.len() < FOOD_COUNT {
+        match new_random_tile(rng, rows, cols, snakes, map_data, SPAWN_WEIGHTING) {
+            Some((x, y)) => {
+                map_data.activate_tile(x, y);
+                food.push((x, y));
+            }
+            None => break,
         }
     }
+}
 
-    fn draw_tile(&mut self, x: f32, y: f32, win: &mut RenderWindow) {
-        let pos_x = x * self.scale;
-        let pos_y = y * self.scale;
-
-        self.rect.set_position((pos_x, pos_y));
-        self.rect.set_size((self.scale, self.scale));
-        self.rect.set_origin((0.0, 0.0));
-
-        let mut col = Color::rgb(21, 21, 21);
-
-        match self.tile_type {
-            TileType::Active => {
-                col = Color::GREEN;
-            }
-            TileType::Blocked => {
-                col = Color::BLACK;
-            }
-            _ => {}
+/// clear every item off the map, put the food from the map file back and
+/// top food and poison up again, so a game starts the same for a given rng
+fn reset_items(
+    rng: &mut StdRng,
+    rows: i32,
+    cols: i32,
+    snakes: &[&Snake],
+    placed_food: &[(i32, i32)],
+    map_data: &mut Map,
+    food: &mut Vec<(i32, i32)>,
+) {
+    let items = [
+        TileType::Active,
+        TileType::Poison,
+        TileType::Bonus,
+        TileType::Shrink,
+        TileType::Boost,
+    ];
+    for item in items.iter().cloned() {
+        for (x, y) in map_data.tiles_of(item) {
+            map_data.deactivate_tile(x, y);
         }
+    }
 
-        self.rect.set_fill_color(col);
-
-        win.draw(&self.rect);
+    food.clear();
+    for &(x, y) in placed_food.iter() {
+        map_data.activate_tile(x, y);
+        food.push((x, y));
     }
+    spawn_food(rng, rows, cols, snakes, map_data, food);
+    spawn_poison(rng, rows, cols, snakes, map_data);
 }
 
-// -----------------------------------
-// MAP
-// -----------------------------------
-#[allow(dead_code)]
-struct Map<'a> {
-    tiles: Vec<Tile<'a>>,
-    width: i32,
-    height: i32,
+/// MOVING_OBSTACLES hazards on free tiles, each heading a random way
+fn spawn_obstacles(
+    rng: &mut StdRng,
+    rows: i32,
+    cols: i32,
+    snakes: &[&Snake],
+    map_data: &Map,
+) -> Vec<Obstacle> {
+    let headings = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+    (0..MOVING_OBSTACLES)
+        .filter_map(|_| {
+            let cell = new_random_tile(rng, rows, cols, snakes, map_data, SPAWN_WEIGHTING)?;
+            let heading = headings[rand_range(rng, 0, headings.len() as i32) as usize];
+            Some(Obstacle::new(cell, heading))
+        })
+        .collect()
 }
 
-impl<'a> Map<'a> {
-    fn new(width: i32, height: i32, map_data: Vec<Tile<'a>>) -> Self {
-        Self {
-            tiles: map_data,
-            width,
-            height,
+/// top the map back up to POISON_TILES poison tiles, or as many as fit
+fn spawn_poison(rng: &mut StdRng, rows: i32, cols: i32, snakes: &[&Snake], map_data: &mut Map) {
+    while map_data.count_tiles(TileType::Poison) < POISON_TILES {
+        match new_random_tile(rng, rows, cols, snakes, map_data, SPAWN_WEIGHTING) {
+            Some((x, y)) => map_data.poison_tile(x, y),
+            None => break,
         }
     }
+}
 
-    /// get tile row/column coord from screen coord
-    fn get_tile_coord(&self, x: i32, y: i32) -> (i32, i32) {
-        let cx = x / BLOCK_SIZE as i32;
-        let cy = y / BLOCK_SIZE as i32;
-        (cx, cy)
+/// rewind the snake if a charge and enough history are available,
+/// returns true if the run is over instead. `tick` is the one it died on
+fn on_death(
+    snake: &mut Snake,
+    history: &mut History,
+    charges: &mut u32,
+    timeline: &mut Option<Timeline>,
+    tick: usize,
+) -> bool {
+    let died_at = snake.head_tile();
+    if rewind(snake, history, charges, REWIND_TICKS) {
+        if let Some(tl) = timeline.as_mut() {
+            tl.event(tick, "rewind", died_at);
+        }
+        return false;
     }
 
-    fn is_tile_active(&self, x: i32, y: i32) -> bool {
-        let coord = x + self.width * y;
-        if coord < 0 {
-            return false;
-        }
-        if let Some(t) = self.tiles.get(coord as usize) {
-            if t.tile_type == TileType::Active {
-                return true;
-            }
+    if let Some(tl) = timeline.as_mut() {
+        tl.event(tick, "death", snake.head_tile());
+        if let Err(e) = tl.dump(TIMELINE_PATH) {
+            println!("failed to write timeline: {}", e);
         }
-        false
     }
 
-    fn is_tile_blocked(&self, x: i32, y: i32) -> bool {
-        let coord = x + self.width * y;
-        if coord < 0 {
-            return false;
-        }
-        if let Some(t) = self.tiles.get(coord as usize) {
-            if t.tile_type == TileType::Blocked {
-                return true;
-            }
-        }
-        false
+    true
+}
+
+/// direction held on gamepad `id`, the d-pad wins over the left stick
+fn pad_action(id: u32) -> Option<Action> {
+    if !joystick::is_connected(id) {
+        return None;
     }
 
-    fn activate_tile(&mut self, x: i32, y: i32) {
-        let coord = x + self.width * y;
-        if coord < 0 {
-            return;
-        }
-        if let Some(t) = self.tiles.get_mut(coord as usize) {
-            t.tile_type = TileType::Active;
-        }
+    let pov_x = joystick::axis_position(id, Axis::PovX);
+    let pov_y = joystick::axis_position(id, Axis::PovY);
+    let stick_x = joystick::axis_position(id, Axis::X);
+    let stick_y = joystick::axis_position(id, Axis::Y);
+
+    stick_action(pov_x, pov_y, JOYSTICK_DEADZONE)
+        .or_else(|| stick_action(stick_x, stick_y, JOYSTICK_DEADZONE))
+}
+
+/// sound effect from file, None (and a note on stdout) if it can't be loaded
+fn load_sfx(path: &str) -> Option<SfBox<SoundBuffer>> {
+    let buffer = SoundBuffer::from_file(path);
+    if buffer.is_none() {
+        println!("failed to load sound {}, playing without it", path);
     }
+    buffer
+}
 
-    fn deactivate_tile(&mut self, x: i32, y: i32) {
-        let coord = x + self.width * y;
-        if coord < 0 {
-            return;
-        }
-        if let Some(t) = self.tiles.get_mut(coord as usize) {
-            t.tile_type = TileType::NonActive;
-        }
+fn play_sfx(sound: &mut Option<Sound>) {
+    if let Some(s) = sound.as_mut() {
+        s.play();
     }
+}
 
-    fn draw(&mut self, win: &mut RenderWindow) {
-        let mut i = 0;
-        // draw 1d array as a 2d array
-        for t in self.tiles.iter_mut() {
-            let x = i % 32;
-            let y = i / 32;
-            t.draw_tile(x as f32, y as f32, win);
-            i = i + 1;
-        }
+/// text to show `elapsed_ms` into a countdown, None once it's over
+fn countdown_label(elapsed_ms: i32) -> Option<String> {
+    if elapsed_ms < COUNTDOWN_MS {
+        let secs = (COUNTDOWN_MS - elapsed_ms + 999) / 1000;
+        return Some(secs.to_string());
+    }
+    if elapsed_ms < COUNTDOWN_MS + GO_MS {
+        return Some("Go!".to_string());
     }
+    None
 }
 
-// -----------------------------------
-// FUNCS
-// -----------------------------------
-fn rand_range(min_value: i32, max_value: i32) -> i32 {
-    use rand::{thread_rng, Rng};
-    use std::cmp::{max, min};
+/// where F5 saves a `mode` game, hardcore runs get a file of their own
+fn save_path(mode: Mode) -> String {
+    if mode == Mode::Hardcore {
+        return hardcore_save_path(SAVE_PATH);
+    }
+    SAVE_PATH.to_string()
+}
 
-    let mut rng = thread_rng();
+/// delete the hardcore save, for a run that can't be gone back to. any
+/// other save is left alone
+fn wipe_hardcore_save() {
+    if let Err(e) = wipe_save(&save_path(Mode::Hardcore)) {
+        println!("failed to delete save: {}", e);
+    }
+}
 
-    let min_v = min(min_value, max_value);
-    let max_v = max(min_value, max_value);
+/// best score saved by a previous run, 0 if missing or unreadable
+fn load_high_score() -> u32 {
+    std::fs::read_to_string(HIGH_SCORE_PATH)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0)
+}
 
-    let result: i32 = rng.gen_range(min_v, max_v);
+fn save_high_score(score: u32) {
+    if let Err(e) = std::fs::write(HIGH_SCORE_PATH, score.to_string()) {
+        println!("failed to save high score: {}", e);
+    }
+}
 
-    result
+/// ms between moves for a snake with `tail_len` segments,
+/// shrinking from `base` by `ramp` ms per segment down to `floor`
+fn tick_interval(base: i32, ramp: i32, tail_len: usize, floor: i32) -> i32 {
+    let ramp = (tail_len as i32).saturating_mul(ramp);
+    floor.max(base.saturating_sub(ramp))
 }
 
-fn on_key_down(map: &mut HashMap<&Key, bool>, key: &Key) {
-    if let Some(x) = map.get_mut(key) {
-        *x = true;
+/// fraction (0..1) of a `interval_ms` move that `elapsed_ms` covers
+fn render_offset(elapsed_ms: i32, interval_ms: i32) -> f32 {
+    if interval_ms <= 0 {
+        return 1.0;
     }
+    (elapsed_ms as f32 / interval_ms as f32).clamp(0.0, 1.0)
 }
 
-fn on_key_up(map: &mut HashMap<&Key, bool>, key: &Key) {
-    if let Some(x) = map.get_mut(key) {
-        *x = false;
+/// load or build a map and apply the start of run tweaks
+/// (safe zone, obstacle shuffle), blank and random maps are `size` (cols, rows).
+/// only map files carry any MapInfo
+fn load_map(
+    source: &MapSource,
+    size: (i32, i32),
+    rng: &mut StdRng,
+) -> std::result::Result<(Map, MapInfo), Box<dyn Error>> {
+    let (map_w, map_h) = size;
+    let (mut map, info) = match source {
+        // the object layer belongs to the default map only
+        MapSource::File(p) if p == MAP_PATH && Path::new(OBJECTS_PATH).exists() => {
+            load_layered(p, OBJECTS_PATH)
+                .map_err(|e| format!("failed to load map layers: {}", e))?
+        }
+        MapSource::File(p) => {
+            let (map_data, map_w, map_h, info) =
+                load_from_file(p).map_err(|e| format!("failed to load map {}: {}", p, e))?;
+            (Map::new(map_w, map_h, map_data), info)
+        }
+        MapSource::Blank => (
+            Map::new(map_w, map_h, blank_map(map_w, map_h)),
+            MapInfo::default(),
+        ),
+        MapSource::Random => {
+            let tiles = generate_map(rng, map_w, map_h, RANDOM_WALL_DENSITY, START_CELL);
+            (Map::new(map_w, map_h, tiles), MapInfo::default())
+        }
+    };
+
+    let start = info.start.unwrap_or(START_CELL);
+    if SAFE_START_ZONE {
+        map.clear_spawn_zone(start, SAFE_START_RADIUS);
+    }
+    if SHUFFLE_OBSTACLES {
+        // keep the spawn tile and the couple in front of it free
+        let (sx, sy) = start;
+        let keep_clear = [(sx, sy), (sx + 1, sy), (sx + 2, sy)];
+        map.shuffle_obstacles(rng, &keep_clear);
     }
+
+    Ok((map, info))
 }
 
-// TODO: clean up / improve ?
-fn new_random_tile<'a>(
-    rows: i32,
-    cols: i32,
-    current_head: &Head<'a>,
-    current_tail: &Vec<Tail<'a>>,
-    map_data: &Map<'a>,
-) -> (i32, i32) {
-    loop {
-        let rng_x = rand_range(1, rows - 1);
-        let rng_y = rand_range(1, cols - 1);
+/// a map that doesn't match the window is cut off or leaves a gap
+fn warn_map_size(map: &Map, grid: (i32, i32)) {
+    if (map.width, map.height) != grid {
+        println!(
+            "map is {}x{} tiles but the window fits {}x{}",
+            map.width, map.height, grid.0, grid.1
+        );
+    }
+}
 
-        if rng_x == (current_head.get_x() / BLOCK_SIZE) as i32
-            || rng_y == (current_head.get_y() / BLOCK_SIZE) as i32
-        {
-            // println!("was on head !");
-            continue;
-        }
+/// `start`, or the nearest cell to it a snake facing `dir` can spawn on if a
+/// wall is on the first tile it moves to or under its `len` tile body
+fn spawn_cell(
+    map: &Map,
+    start: (i32, i32),
+    dir: Direction,
+    len: usize,
+) -> std::result::Result<(i32, i32), Box<dyn Error>> {
+    let (dx, dy) = dir.offset();
+    let (sx, sy) = start;
+    map.nearest_cell_where(sx, sy, |x, y| {
+        !map.is_tile_blocked(x + dx, y + dy)
+            && body_cells((x, y), dir, len)
+                .all(|(bx, by)| map.in_bounds(bx, by) && !map.is_tile_blocked(bx, by))
+    })
+    .ok_or_else(|| {
+        format!(
+            "map has no open line of {} cells to spawn the snake on",
+            len
+        )
+        .into()
+    })
+}
 
-        let on_tail = current_tail.iter().any(|x| {
-            if (x.get_x() / BLOCK_SIZE) as i32 == rng_x || (x.get_y() / BLOCK_SIZE) as i32 == rng_y
-            {
-                return true;
-            }
-            false
-        });
+/// load_map plus where games on it start, an error if the map can't be read
+/// or has no room for a snake. anything random about the map comes from
+/// `map_seed` so a replay can make it again
+fn open_map(
+    source: &MapSource,
+    size: (i32, i32),
+    map_seed: u64,
+    start_length: usize,
+) -> std::result::Result<(Map, MapInfo, Level), Box<dyn Error>> {
+    let (map, info) = load_map(source, size, &mut StdRng::seed_from_u64(map_seed))?;
+    let start = info.start.unwrap_or(START_CELL);
+    let level = Level {
+        name: source.name(),
+        map_seed,
+        spawn: spawn_cell(&map, start, Direction::Right, start_length)?,
+        rival_spawn: spawn_cell(&map, rival_start(&map), Direction::Left, start_length)?,
+        length: start_length,
+        placed_food: map.tiles_of(TileType::Active),
+    };
+    Ok((map, info, level))
+}
 
-        if on_tail {
-            // println!("was on tail !");
-            continue;
-        }
+/// player two starts mirrored from player one, facing the other way
+fn rival_start(map: &Map) -> (i32, i32) {
+    (map.width - 1 - START_CELL.0, map.height - 1 - START_CELL.1)
+}
 
-        if map_data.is_tile_blocked(rng_x, rng_y) {
-            // println!("was on blocked tile!");
-            continue;
-        }
+/// draw a line of HUD text at x, y
+fn draw_hud_text(text: &str, x: f32, y: f32, r: &mut dyn Renderer) {
+    r.draw_text(text, x, y, 16, 1.0);
+}
 
-        return (rng_x, rng_y);
+/// draw remaining rewind charges as pips in the top right corner
+/// of a `game_width` px wide game
+fn draw_rewind_charges(charges: u32, game_width: f32, r: &mut dyn Renderer) {
+    let size = TILE_H * 0.5;
+    for i in 0..charges {
+        let x = game_width - (i + 1) as f32 * (size + 4.0) - 4.0;
+        r.draw_rect(x, 4.0, size, size, Color::CYAN);
     }
 }
 
-fn load_from_file<'a>() -> Result<Vec<Tile<'a>>> {
-    let mut tiles = Vec::new();
-
-    let file = File::open("assets/map/data.txt")?;
-    let buffer = BufReader::new(file);
-
-    for line in buffer.lines() {
-        let v_line: Vec<char> = line?.chars().collect();
-        for x in v_line.iter() {
-            match *x {
-                '0' => {
-                    tiles.push(Tile::new(BLOCK_SIZE, TileType::NonBlocked));
-                }
-                '1' => {
-                    tiles.push(Tile::new(BLOCK_SIZE, TileType::Blocked));
-                }
-                '2' => {
-                    tiles.push(Tile::new(BLOCK_SIZE, TileType::Active));
-                }
-                _ => {}
+/// the whole map shrunk into `rect`: walls, food and the snakes
+fn draw_minimap(
+    r: &mut dyn Renderer,
+    map: &Map,
+    snakes: &[&Snake],
+    food: &[(i32, i32)],
+    rect: FloatRect,
+    theme: &Theme,
+) {
+    let mut back = theme.floor;
+    back.a = 200;
+    r.draw_rect(rect.left, rect.top, rect.width, rect.height, back);
+
+    let cell_w = rect.width / map.width.max(1) as f32;
+    let cell_h = rect.height / map.height.max(1) as f32;
+    let mut mark = |(x, y): (i32, i32), color: Color| {
+        let px = rect.left + x as f32 * cell_w;
+        let py = rect.top + y as f32 * cell_h;
+        r.draw_rect(px, py, cell_w, cell_h, color);
+    };
+    for y in 0..map.height {
+        for x in 0..map.width {
+            if map.is_tile_blocked(x, y) {
+                mark((x, y), theme.wall);
             }
         }
     }
+    for &cell in food.iter() {
+        mark(cell, theme.food);
+    }
+    for s in snakes.iter() {
+        for t in s.tail.iter() {
+            mark(t.cell(), theme.tail);
+        }
+        mark(s.head_tile(), theme.head);
+    }
+}
 
-    Ok(tiles)
+/// view showing the whole `game_size` area as large as fits in `window_size`
+/// without stretching, centered with bars down the sides or top and bottom
+fn letterbox_view(window_size: Vector2u, game_size: Vector2f) -> SfBox<View> {
+    let mut view = View::from_rect(&FloatRect::new(0.0, 0.0, game_size.x, game_size.y));
+    let window_ratio = window_size.x as f32 / window_size.y.max(1) as f32;
+    let game_ratio = game_size.x / game_size.y;
+
+    // viewport is in fractions (0..1) of the window
+    let (w, h) = if window_ratio > game_ratio {
+        (game_ratio / window_ratio, 1.0)
+    } else {
+        (1.0, window_ratio / game_ratio)
+    };
+    view.set_viewport(&FloatRect::new((1.0 - w) / 2.0, (1.0 - h) / 2.0, w, h));
+    view
 }
 
-fn run(width: u32, height: u32) {
-    let mut window = RenderWindow::new((width, height), "sfml", Style::CLOSE, &Default::default());
+/// a window the config's size, or the screen's best fullscreen mode,
+/// letterboxed round the `width` x `height` game, drawing at most
+/// the config's framerate
+fn create_window(
+    (width, height): (u32, u32),
+    config: &Config,
+    fullscreen: bool,
+) -> std::result::Result<RenderWindow, Box<dyn Error>> {
+    let (window_w, window_h) = config.window_size();
+    let mut window = if fullscreen {
+        // modes are sorted best first
+        let mode = VideoMode::fullscreen_modes()
+            .first()
+            .copied()
+            .unwrap_or_else(VideoMode::desktop_mode);
+        RenderWindow::new(mode, WINDOW_TITLE, Style::FULLSCREEN, &Default::default())
+    } else {
+        RenderWindow::new(
+            (window_w, window_h),
+            WINDOW_TITLE,
+            Style::CLOSE,
+            &Default::default(),
+        )
+    };
+    if !window.is_open() {
+        return Err("failed to create window".into());
+    }
+    // no icon file just leaves the default one
+    if let Some(icon) = Image::from_file(ICON_PATH) {
+        let size = icon.size();
+        window.set_icon(size.x, size.y, icon.pixel_data());
+    }
     window.set_mouse_cursor_visible(true);
-    window.set_framerate_limit(30);
+    window.set_framerate_limit(config.framerate);
+    // one KeyPressed per press, holding a key must not keep stepping
+    window.set_key_repeat_enabled(!config.turn_based);
+    let game_size = Vector2f::new(width as f32, height as f32);
+    window.set_view(&letterbox_view(window.size(), game_size));
+    Ok(window)
+}
 
-    let mut is_running = true;
-    let mut pause = false;
-    let mut add_segment = false;
-    let mut update_snake = Clock::start();
+fn run(
+    mut config: Config,
+    map_path: Option<String>,
+    trace: bool,
+    seed: Option<u64>,
+    playback: Option<Replay>,
+) -> std::result::Result<(), Box<dyn Error>> {
+    let (width, height) = (config.width, config.height);
+    let mut fullscreen = false;
+    let mut window = create_window((width, height), &config, fullscreen)?;
+    // score the window title shows, None to set it on the next frame
+    let mut title_score: Option<u32> = None;
+
+    let mut rng = make_rng(seed);
+    let grid = grid_size(width, height);
+    let maps: Vec<MapSource> = match map_path {
+        // a map given on the command line skips the map select screen
+        Some(p) => vec![MapSource::File(p)],
+        None => {
+            // map files to pick from, the object layer is not a map of its own
+            let mut maps: Vec<MapSource> = list_maps(MAP_DIR)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|p| Path::new(p) != Path::new(OBJECTS_PATH))
+                .map(MapSource::File)
+                .collect();
+            if maps.is_empty() {
+                maps.push(MapSource::Blank);
+            }
+            maps.push(MapSource::Random);
+            maps
+        }
+    };
+    let mut map_choice = maps
+        .iter()
+        .position(|m| matches!(m, MapSource::File(p) if p == MAP_PATH))
+        .unwrap_or(0);
+    // only ask when there is a choice to make, a replay brings its own map
+    let mut state = if maps.len() > 1 && playback.is_none() {
+        AppState::MapSelect
+    } else {
+        AppState::Menu
+    };
+    let mut difficulty = Difficulty::Normal;
+    let mut show_grid = config.show_grid;
+    // only drawn with follow_camera on, the whole map is in view otherwise
+    let mut show_minimap = true;
+    // debug fps counter, frames are counted over FPS_SAMPLE_MS windows
+    let mut show_fps = false;
+    let mut fps_clock = Clock::start();
+    // eating bursts, only drawn
+    let mut particles: Vec<Particle> = vec![];
+    // how long the last frame took, for what runs on real time
+    let mut frame_clock = Clock::start();
+    let mut fps_frames: u32 = 0;
+    let mut fps: f32 = 0.0;
+    let mut theme_name = config.theme;
+    let mut theme = theme_name.theme();
+    let mut update_snake = MoveClock::start();
+    let mut mode = Mode::Classic;
+    // paused by the window losing focus rather than by the player
+    let mut focus_paused = false;
+    // counts towards the demo while the menu is left alone
+    let mut idle = IdleTimer::default();
+    // the mode picked on the menu while the idle demo plays in its place
+    let mut attract: Option<Mode> = None;
+    let mut timeline = if trace { Some(Timeline::new()) } else { None };
+    let mut high_score = load_high_score();
+    let mut leaderboard = load_leaderboard(SCORES_PATH);
+    let mut name_entry = String::new();
+    // where leaving the leaderboard screen goes back to
+    let mut leaderboard_back = AppState::Menu;
+    let mut stats = load_stats(STATS_PATH);
+    let finale_clock = Clock::start();
+    let font =
+        Font::from_file(FONT_PATH).ok_or_else(|| format!("failed to load font {}", FONT_PATH))?;
+    // buffers must outlive the sounds playing them
+    let eat_buffer = load_sfx(EAT_SFX_PATH);
+    let death_buffer = load_sfx(DEATH_SFX_PATH);
+    let mut eat_sound = eat_buffer.as_ref().map(|b| Sound::with_buffer(b));
+    let mut death_sound = death_buffer.as_ref().map(|b| Sound::with_buffer(b));
+    let mut music = Music::from_file(MUSIC_PATH);
+    match music.as_mut() {
+        Some(m) => m.set_looping(true),
+        None => println!("failed to load music {}, playing without it", MUSIC_PATH),
+    }
+    // kept across restarts
+    let mut muted = false;
+    // only advances while playing, so slime never expires during pause
+    let mut tick: u64 = 0;
+    let mut step_requested = false;
+    // running since the last crash, None once the shake is over
+    let mut shake_clock: Option<Clock> = None;
+    // the snake flashes while flash_clock is short of flash_until_ms
+    let flash_clock = Clock::start();
+    let mut flash_until_ms: i32 = 0;
 
     // key mapings
-    let mut keys_hm: HashMap<&Key, bool> = HashMap::new();
-    keys_hm.insert(&Key::W, false);
-    keys_hm.insert(&Key::D, false);
-    keys_hm.insert(&Key::A, false);
-    keys_hm.insert(&Key::S, false);
+    let mut bindings = Bindings::from_file(CONTROLS_PATH)?;
+    bindings.validate()?;
+    let mut held_keys = KeyState::default();
+    let mut remap = Remap::default();
+    // player two steers with the arrow keys, taken before player one's bindings
+    let rival_bindings = Bindings::arrows();
+    let mut rival_keys = KeyState::default();
+    // direction the gamepad held last frame
+    let mut pad_held: Option<Action> = None;
+
+    // replaced once a map is picked on the select screen
+    let (mut map, mut map_info, mut level) = match playback.as_ref().map(|p| &p.header) {
+        // a replay is played on the map and by the rules it was recorded with
+        Some(h) => {
+            mode = Mode::from_name(&h.mode).ok_or_else(|| format!("replay mode {}?", h.mode))?;
+            difficulty = Difficulty::from_name(&h.difficulty)
+                .ok_or_else(|| format!("replay difficulty {}?", h.difficulty))?;
+            config.wrap = Some(h.wrap);
+            let source = match h.map.as_str() {
+                "Blank" => MapSource::Blank,
+                "Random" => MapSource::Random,
+                name => maps
+                    .iter()
+                    .find(|m| m.name() == name)
+                    .cloned()
+                    .ok_or_else(|| format!("replay map {} isn't in {}", name, MAP_DIR))?,
+            };
+            open_map(&source, h.size, h.map_seed, h.start_length)?
+        }
+        None => open_map(&maps[map_choice], grid, rng.gen(), config.start_length)?,
+    };
+    // why the last map picked couldn't be played, shown on the select screen
+    let mut map_error: Option<String> = None;
 
     // objs
-    let mut head = Head::new(150.0, 150.0, BLOCK_SIZE, Color::WHITE, Direction::Right);
-    let mut tail: Vec<Tail<'_>> = vec![];
+    let mut game = Game::new(Mode::Classic, &level);
+    // size comes from the map file, not the window
+    let mut rows = map.width;
+    let mut cols = map.height;
+    warn_map_size(&map, grid);
+    game.food = level.placed_food.clone();
+    spawn_food(
+        &mut rng,
+        rows,
+        cols,
+        &all_snakes(&game.snake, &game.rival),
+        &mut map,
+        &mut game.food,
+    );
+    spawn_poison(
+        &mut rng,
+        rows,
+        cols,
+        &all_snakes(&game.snake, &game.rival),
+        &mut map,
+    );
+
+    while window.is_open() {
+        let frame_ms = frame_clock.restart().as_milliseconds();
 
-    // MAP SIZE = 32 X 24
-    let rows = (width / 25) as i32;
-    let cols = (height / 25) as i32;
-    let map_data = load_from_file().expect("failed to find file");
-    let mut map = Map::new(rows, cols, map_data);
-
-    while is_running && window.is_open() {
         // --------------------------
         // inputs
         // --------------------------
         while let Some(ev) = window.poll_event() {
+            idle.reset();
             match ev {
                 Event::Closed => {
-                    is_running = false;
+                    window.close();
+                }
+                // keep the grid square whatever shape the window is dragged to
+                Event::Resized {
+                    width: w,
+                    height: h,
+                } => {
+                    let game_size = Vector2f::new(width as f32, height as f32);
+                    window.set_view(&letterbox_view(Vector2u::new(w, h), game_size));
+                }
+                // tabbing away pauses, coming back only resumes that pause
+                Event::LostFocus if state == AppState::Playing => {
+                    state = AppState::Paused;
+                    update_snake.pause();
+                    focus_paused = true;
+                }
+                Event::GainedFocus => {
+                    if focus_paused && state == AppState::Paused {
+                        state = AppState::Playing;
+                        update_snake.resume();
+                    }
+                    focus_paused = false;
+                }
+
+                Event::TextEntered { unicode }
+                    if state == AppState::NameEntry
+                        && unicode.is_ascii_alphabetic()
+                        && name_entry.len() < NAME_LEN =>
+                {
+                    name_entry.push(unicode.to_ascii_uppercase());
                 }
 
+                // any key ends the idle demo, back to the menu as it was left
+                Event::KeyPressed { .. } if attract.is_some() => {
+                    mode = attract.take().unwrap_or(mode);
+                    if let Some(m) = music.as_mut() {
+                        m.stop();
+                    }
+                    held_keys = KeyState::default();
+                    particles.clear();
+                    shake_clock = None;
+                    state = AppState::Menu;
+                }
                 Event::KeyPressed { code, .. } => match code {
-                    Key::Escape => is_running = false,
-                    Key::P => pause = !pause,
-                    Key::W => on_key_down(&mut keys_hm, &Key::W),
-                    Key::A => on_key_down(&mut keys_hm, &Key::A),
-                    Key::S => on_key_down(&mut keys_hm, &Key::S),
-                    Key::D => on_key_down(&mut keys_hm, &Key::D),
-                    _ => {}
-                },
-                Event::KeyReleased { code, .. } => match code {
-                    Key::W => on_key_up(&mut keys_hm, &Key::W),
-                    Key::A => on_key_up(&mut keys_hm, &Key::A),
-                    Key::S => on_key_up(&mut keys_hm, &Key::S),
-                    Key::D => on_key_up(&mut keys_hm, &Key::D),
-                    _ => {}
+                    // leaving part way keeps the old keys
+                    Key::Escape if state == AppState::Controls => state = AppState::Menu,
+                    Key::Escape => window.close(),
+                    Key::Return if state == AppState::NameEntry && name_entry.len() == NAME_LEN => {
+                        insert_score(&mut leaderboard, &name_entry, game.score);
+                        if let Err(e) = save_leaderboard(SCORES_PATH, &leaderboard) {
+                            println!("failed to save leaderboard: {}", e);
+                        }
+                        state = AppState::Leaderboard;
+                    }
+                    Key::BackSpace if state == AppState::NameEntry => {
+                        name_entry.pop();
+                    }
+                    // letters are typed through TextEntered, nothing else acts
+                    _ if state == AppState::NameEntry => {}
+                    _ if state == AppState::Controls => {
+                        if let Some(new_bindings) = remap.press(code) {
+                            bindings = new_bindings;
+                            held_keys = KeyState::default();
+                            if let Err(e) = std::fs::write(CONTROLS_PATH, bindings.to_text()) {
+                                println!("failed to save controls: {}", e);
+                            }
+                            state = AppState::Menu;
+                        }
+                    }
+                    Key::L if state == AppState::Menu => {
+                        leaderboard_back = AppState::Menu;
+                        state = AppState::Leaderboard;
+                    }
+                    Key::Return if state == AppState::Leaderboard => state = leaderboard_back,
+                    Key::I if state == AppState::Menu => state = AppState::Stats,
+                    Key::Return if state == AppState::Stats => state = AppState::Menu,
+                    Key::C if state == AppState::Menu => {
+                        remap = Remap::default();
+                        state = AppState::Controls;
+                    }
+                    Key::Return if state == AppState::MapSelect => {
+                        // a broken map is reported and another can be picked
+                        let opened =
+                            open_map(&maps[map_choice], grid, rng.gen(), config.start_length);
+                        let (new_map, new_info, new_level) = match opened {
+                            Ok(opened) => opened,
+                            Err(e) => {
+                                println!("{}", e);
+                                map_error = Some(e.to_string());
+                                continue;
+                            }
+                        };
+                        map_error = None;
+                        map = new_map;
+                        map_info = new_info;
+                        level = new_level;
+                        game = Game::new(Mode::Classic, &level);
+                        rows = map.width;
+                        cols = map.height;
+                        warn_map_size(&map, grid);
+                        game.food = level.placed_food.clone();
+                        spawn_food(
+                            &mut rng,
+                            rows,
+                            cols,
+                            &all_snakes(&game.snake, &game.rival),
+                            &mut map,
+                            &mut game.food,
+                        );
+                        spawn_poison(
+                            &mut rng,
+                            rows,
+                            cols,
+                            &all_snakes(&game.snake, &game.rival),
+                            &mut map,
+                        );
+                        state = AppState::Menu;
+                    }
+                    // a replay keeps the rules it was recorded with
+                    Key::Num0 | Key::Num1 | Key::Num2 | Key::Num3 | Key::Tab
+                        if state == AppState::Menu && playback.is_some() => {}
+                    Key::Num0 if state == AppState::Menu => difficulty = Difficulty::Slow,
+                    Key::Num1 if state == AppState::Menu => difficulty = Difficulty::Easy,
+                    Key::Num2 if state == AppState::Menu => difficulty = Difficulty::Normal,
+                    Key::Num3 if state == AppState::Menu => difficulty = Difficulty::Hard,
+                    Key::Tab if state == AppState::Menu => mode = mode.next(),
+                    Key::Return if state == AppState::Menu => {
+                        let playback_seed = playback.as_ref().map(|p| p.seed);
+                        let song = music.as_mut().filter(|_| !muted);
+                        let wrap = config.wrap.unwrap_or(difficulty.settings().wrap);
+                        start_game(
+                            &mut game,
+                            mode,
+                            difficulty,
+                            wrap,
+                            &level,
+                            &mut map,
+                            &mut rng,
+                            playback_seed,
+                            song,
+                        );
+                        state = AppState::Countdown;
+                    }
+                    // a hardcore run that ended is over, it can't be restarted
+                    Key::Return if state == AppState::GameOver && mode == Mode::Hardcore => {
+                        state = AppState::Menu;
+                    }
+                    Key::R
+                        if (state == AppState::GameOver && mode != Mode::Hardcore)
+                            || state == AppState::Won =>
+                    {
+                        let playback_seed = playback.as_ref().map(|p| p.seed);
+                        let song = music.as_mut().filter(|_| !muted);
+                        let wrap = config.wrap.unwrap_or(difficulty.settings().wrap);
+                        start_game(
+                            &mut game,
+                            mode,
+                            difficulty,
+                            wrap,
+                            &level,
+                            &mut map,
+                            &mut rng,
+                            playback_seed,
+                            song,
+                        );
+                        state = AppState::Countdown;
+                    }
+                    Key::G => show_grid = !show_grid,
+                    Key::N => show_minimap = !show_minimap,
+                    // the game carries on, only the window is swapped
+                    Key::F11 => {
+                        fullscreen = !fullscreen;
+                        window = create_window((width, height), &config, fullscreen)?;
+                        title_score = None;
+                    }
+                    Key::F3 => show_fps = !show_fps,
+                    // only a game in progress saves, versus games never do
+                    Key::F5 if game.rival.is_none() && state.can_save() => {
+                        let mut cells = vec![game.snake.head_tile()];
+                        cells.extend(game.snake.tail.iter().map(|t| t.cell()));
+                        let saved = SavedGame {
+                            map: level.name.clone(),
+                            mode: format!("{:?}", mode),
+                            cells,
+                            dir: game.snake.head.dir,
+                            score: game.score,
+                            food: game.food.clone(),
+                        };
+                        if let Err(e) = save_game(&save_path(mode), &saved) {
+                            println!("failed to save game: {}", e);
+                        }
+                    }
+                    // a hardcore run only picks up again from the menu
+                    Key::F9
+                        if game.rival.is_none()
+                            && state.can_load()
+                            && (mode != Mode::Hardcore || state == AppState::Menu) =>
+                    {
+                        let hardcore = mode == Mode::Hardcore;
+                        let path = save_path(mode);
+                        let saved = match load_game(&path) {
+                            Some(saved) => saved,
+                            None => {
+                                println!("no usable save in {}, ignoring", path);
+                                continue;
+                            }
+                        };
+                        // only modes F5 could have saved in that file
+                        let solo = |m: Mode| m != Mode::Versus && m != Mode::VsComputer;
+                        let saved_mode = match Mode::from_name(&saved.mode) {
+                            Some(m) if solo(m) && (m == Mode::Hardcore) == hardcore => m,
+                            _ => {
+                                println!("{}: can't load a {} game, ignoring", path, saved.mode);
+                                continue;
+                            }
+                        };
+                        // back onto the map it was saved on, loaded only if it isn't this one
+                        let choice = maps.iter().position(|m| m.name() == saved.map);
+                        let opened = match choice {
+                            _ if saved.map == level.name => None,
+                            Some(i) => {
+                                match open_map(&maps[i], grid, rng.gen(), config.start_length) {
+                                    Ok(opened) => Some((i, opened)),
+                                    Err(e) => {
+                                        println!("{}: {}, ignoring", path, e);
+                                        continue;
+                                    }
+                                }
+                            }
+                            None => {
+                                println!("{}: no map {}, ignoring", path, saved.map);
+                                continue;
+                            }
+                        };
+                        let target = opened.as_ref().map_or(&map, |(_, (m, _, _))| m);
+                        if !saved.fits(target) {
+                            println!("{}: snake or food off the map or on a wall, ignoring", path);
+                            continue;
+                        }
+                        if let Some((i, (new_map, new_info, new_level))) = opened {
+                            map_choice = i;
+                            map = new_map;
+                            map_info = new_info;
+                            level = new_level;
+                            rows = map.width;
+                            cols = map.height;
+                            warn_map_size(&map, grid);
+                        }
+                        mode = saved_mode;
+
+                        let song = music.as_mut().filter(|_| !muted);
+                        let wrap = config.wrap.unwrap_or(difficulty.settings().wrap);
+                        start_game(
+                            &mut game, mode, difficulty, wrap, &level, &mut map, &mut rng, None,
+                            song,
+                        );
+                        // a replay starts from the spawn, it can't rebuild a loaded game
+                        game.recording = None;
+
+                        let (hx, hy) = saved.cells[0];
+                        game.snake.head.set_cell(hx, hy);
+                        game.snake.head.set_direction(saved.dir);
+                        game.snake.grow_pending = false;
+                        game.snake.tail = saved.cells[1..]
+                            .iter()
+                            .map(|&(x, y)| Tail::from_cell(x, y, (TILE_W, TILE_H)))
+                            .collect();
+
+                        for &(x, y) in game.food.iter() {
+                            map.deactivate_tile(x, y);
+                        }
+                        game.food = saved.food;
+                        for &(x, y) in game.food.iter() {
+                            map.activate_tile(x, y);
+                        }
+
+                        game.score = saved.score;
+                        // kept off the loaded snake rather than the spawn
+                        game.obstacles = spawn_obstacles(
+                            &mut rng,
+                            rows,
+                            cols,
+                            &all_snakes(&game.snake, &game.rival),
+                            &map,
+                        );
+                        state = AppState::Countdown;
+                        // used up by loading, so a hardcore run can't be gone back to
+                        if hardcore {
+                            wipe_hardcore_save();
+                        }
+                    }
+                    Key::M => {
+                        muted = !muted;
+                        if let Some(m) = music.as_mut() {
+                            if muted {
+                                m.pause();
+                            } else if state != AppState::Menu && state != AppState::MapSelect {
+                                // only resume once the game has begun
+                                m.play();
+                            }
+                        }
+                    }
+                    Key::T => {
+                        theme_name = theme_name.next();
+                        theme = theme_name.theme();
+                    }
+                    Key::F2 => {
+                        if let Some(tl) = timeline.as_ref() {
+                            if let Err(e) = tl.dump(TIMELINE_PATH) {
+                                println!("failed to write timeline: {}", e);
+                            }
+                        }
+                    }
+                    _ => {
+                        let rival_action = rival_bindings
+                            .action(code)
+                            .filter(|_| mode == Mode::Versus && state != AppState::MapSelect);
+                        if let Some(action) = rival_action {
+                            rival_keys.on_key_down(action);
+                            step_requested = true;
+                        } else if let Some(action) = bindings.action(code) {
+                            if state == AppState::MapSelect {
+                                match action {
+                                    Action::Up => map_choice = map_choice.saturating_sub(1),
+                                    Action::Down => {
+                                        map_choice = (map_choice + 1).min(maps.len() - 1)
+                                    }
+                                    _ => {}
+                                }
+                            } else if action == Action::Pause {
+                                state = state.toggle_pause();
+                                focus_paused = false;
+                                // time spent paused must not count towards the next move
+                                if state == AppState::Paused {
+                                    update_snake.pause();
+                                } else {
+                                    update_snake.resume();
+                                }
+                            } else {
+                                held_keys.on_key_down(action);
+                                step_requested = true;
+                            }
+                        }
+                    }
                 },
+                Event::KeyReleased { code, .. } => {
+                    let rival_action = rival_bindings.action(code).filter(|_| mode == Mode::Versus);
+                    if let Some(action) = rival_action {
+                        rival_keys.on_key_up(action);
+                    } else if let Some(action) = bindings.action(code) {
+                        held_keys.on_key_up(action);
+                    }
+                }
                 _ => {}
             }
         }
 
-        if !pause {
+        // a replay has its own game to show, no demo over it
+        let idle_ms = config.demo_idle_ms().filter(|_| playback.is_none());
+        if state == AppState::Menu && idle.tick(frame_ms, idle_ms) {
+            attract = Some(mode);
+            mode = Mode::Demo;
+            let song = music.as_mut().filter(|_| !muted);
+            let wrap = config.wrap.unwrap_or(difficulty.settings().wrap);
+            start_game(
+                &mut game, mode, difficulty, wrap, &level, &mut map, &mut rng, None, song,
+            );
+            state = AppState::Countdown;
+        }
+
+        fps_frames += 1;
+        let sample_ms = fps_clock.elapsed_time().as_milliseconds();
+        if sample_ms >= FPS_SAMPLE_MS {
+            fps = fps_frames as f32 * 1000.0 / sample_ms as f32;
+            fps_frames = 0;
+            fps_clock.restart();
+        }
+
+        // gamepad presses and releases land in the same map as the keys
+        let pad = pad_action(JOYSTICK_ID);
+        if pad != pad_held {
+            if let Some(action) = pad_held {
+                held_keys.on_key_up(action);
+            }
+            if let Some(action) = pad {
+                held_keys.on_key_down(action);
+                step_requested = true;
+            }
+            pad_held = pad;
+        }
+
+        // update snake every so oftern as to not fly off screen
+        let settings = difficulty.settings();
+        let mut tick_ms = tick_interval(
+            (settings.base_interval_ms as f32 / config.speed).round() as i32,
+            settings.ramp_ms_per_segment,
+            game.snake.tail.len(),
+            config.min_interval_ms,
+        );
+        if game.slow_ticks > 0 {
+            tick_ms += SLIME_SLOW_MS;
+        }
+        // a boost speeds the snake up but never past the configured floor
+        tick_ms = boost_interval(tick_ms, game.tick < game.boost_until, BOOST_SCALE)
+            .max(config.min_interval_ms);
+
+        if state == AppState::Countdown {
+            // turns pressed now are queued and taken on the first move
+            game.snake.input(&held_keys);
+            if let Some(r) = game.rival.as_mut() {
+                r.input(&rival_keys);
+            }
 
-            // --------------------------
-            // inputs
-            // --------------------------
-            head.inputs(&keys_hm);
+            if game.countdown.elapsed_time().as_milliseconds() >= COUNTDOWN_MS {
+                update_snake.restart();
+                state = AppState::Playing;
+            }
+        }
 
-            // --------------------------
-            // update
-            // --------------------------
-            // current head pos.
-            let (hx, hy) = map.get_tile_coord(head.get_x() as i32, head.get_y() as i32);
-
-            // check if head is on blocked tile
-            if map.is_tile_blocked(hx, hy) {
-                // reset
-                head.reset(150.0, 150.0);
-                tail.clear();
-            }
-
-            // check if head is on active tile
-            if map.is_tile_active(hx, hy) {
-                map.deactivate_tile(hx, hy);
-                let (new_tile_x, new_tile_y) = new_random_tile(rows, cols, &head, &tail, &map);
-                map.activate_tile(new_tile_x, new_tile_y);
-                add_segment = true;
-            }
-
-            // check head is on same tile as one of the tails.
-            for t in tail.iter_mut() {
-                let (tx, ty) = map.get_tile_coord(t.get_x() as i32, t.get_y() as i32);
-                if tx == hx && ty == hy {
-                    head.reset(150.0, 150.0);
-                    tail.clear();
+        if state == AppState::Playing {
+            game.played_ms += frame_ms;
+
+            // a pass for every move the time since the last frame has room
+            // for, each one dealing with what the move before it ran into
+            let mut moves = 0;
+            loop {
+                // --------------------------
+                // inputs
+                // --------------------------
+                game.snake.input(&held_keys);
+
+                // --------------------------
+                // update
+                // --------------------------
+                // current head pos.
+                let (hx, hy) = game.snake.head_tile();
+
+                // too long since the last food, the combo is over
+                if game.tick - game.last_eat_tick > COMBO_WINDOW_TICKS {
+                    game.multiplier = 1;
+                }
+
+                // walls, food and the snake's own tail were dealt with by the last step
+                let outcome = std::mem::take(&mut game.last_step);
+                let mut died = outcome.died;
+                // moving obstacles are deadly whatever the walls are
+                died |= game.obstacles.iter().any(|o| o.cell == (hx, hy));
+
+                if outcome.ate {
+                    spawn_food(
+                        &mut rng,
+                        rows,
+                        cols,
+                        &all_snakes(&game.snake, &game.rival),
+                        &mut map,
+                        &mut game.food,
+                    );
+                    spawn_poison(
+                        &mut rng,
+                        rows,
+                        cols,
+                        &all_snakes(&game.snake, &game.rival),
+                        &mut map,
+                    );
+                    // no room left for a single food, the snake has filled the board
+                    if game.food.is_empty() {
+                        if let Some(tl) = timeline.as_mut() {
+                            tl.event(game.tick, "won", (hx, hy));
+                        }
+                        state = AppState::Won;
+                    }
+                    if game.bonus.is_none() && rand_unit(&mut rng) < BONUS_CHANCE {
+                        game.bonus = bonus_spawn_tile(
+                            &mut rng,
+                            rows,
+                            cols,
+                            &all_snakes(&game.snake, &game.rival),
+                            &map,
+                            &map_info.bonus_zones,
+                            SPAWN_WEIGHTING,
+                        );
+                        if let Some((bx, by)) = game.bonus {
+                            map.bonus_tile(bx, by);
+                            game.bonus_tick = game.tick;
+                        }
+                    }
+                    if map.count_tiles(TileType::Shrink) == 0 && rand_unit(&mut rng) < SHRINK_CHANCE
+                    {
+                        let cell = new_random_tile(
+                            &mut rng,
+                            rows,
+                            cols,
+                            &all_snakes(&game.snake, &game.rival),
+                            &map,
+                            SPAWN_WEIGHTING,
+                        );
+                        if let Some((sx, sy)) = cell {
+                            map.shrink_tile(sx, sy);
+                        }
+                    }
+                    if map.count_tiles(TileType::Boost) == 0 && rand_unit(&mut rng) < BOOST_CHANCE {
+                        let cell = new_random_tile(
+                            &mut rng,
+                            rows,
+                            cols,
+                            &all_snakes(&game.snake, &game.rival),
+                            &map,
+                            SPAWN_WEIGHTING,
+                        );
+                        if let Some((bx, by)) = cell {
+                            map.boost_tile(bx, by);
+                        }
+                    }
+                    let now = game.tick;
+                    game.multiplier =
+                        if game.food_eaten > 0 && now - game.last_eat_tick <= COMBO_WINDOW_TICKS {
+                            (game.multiplier + 1).min(MAX_MULTIPLIER)
+                        } else {
+                            1
+                        };
+                    game.last_eat_tick = now;
+                    game.score += score_for_eat(1, game.multiplier);
+                    game.food_eaten += 1;
+                    let wall_due = game.food_eaten.is_multiple_of(WALL_EVERY_FOOD);
+                    if GROWING_WALLS && wall_due && game.added_walls.len() < MAX_ADDED_WALLS {
+                        let cell = new_random_tile(
+                            &mut rng,
+                            rows,
+                            cols,
+                            &all_snakes(&game.snake, &game.rival),
+                            &map,
+                            SPAWN_WEIGHTING,
+                        );
+                        // never wall off the tile the snake is about to move onto
+                        let (dx, dy) = game.snake.head.dir.offset();
+                        if let Some((wx, wy)) = cell.filter(|&c| c != (hx + dx, hy + dy)) {
+                            map.block_tile(wx, wy);
+                            game.added_walls.push((wx, wy));
+                        }
+                    }
+                    if mode == Mode::Timed {
+                        game.time_limit += TIMED_FOOD_BONUS_TICKS;
+                    }
+                    if game.score > high_score && mode != Mode::Demo {
+                        high_score = game.score;
+                        save_high_score(high_score);
+                    }
+                    if let Some(tl) = timeline.as_mut() {
+                        tl.event(game.tick, "eat", (hx, hy));
+                    }
+                    if !config.reduced_motion {
+                        burst(&mut particles, (hx, hy));
+                    }
+                    play_sfx(&mut eat_sound);
+                }
+
+                // check if head is on the bonus tile
+                if map.is_tile_bonus(hx, hy) {
+                    map.deactivate_tile(hx, hy);
+                    game.bonus = None;
+                    game.snake.grow();
+                    game.score += score_for_eat(BONUS_SCORE, game.multiplier);
+                    if game.score > high_score && mode != Mode::Demo {
+                        high_score = game.score;
+                        save_high_score(high_score);
+                    }
+                    if let Some(tl) = timeline.as_mut() {
+                        tl.event(game.tick, "bonus", (hx, hy));
+                    }
+                    play_sfx(&mut eat_sound);
+                }
+
+                // check if head is on a shrink tile
+                if map.is_tile_shrink(hx, hy) {
+                    map.deactivate_tile(hx, hy);
+                    // shrink stops at an empty tail
+                    game.snake.shrink(SHRINK_AMOUNT);
+                    game.score += score_for_eat(SHRINK_SCORE, game.multiplier);
+                    if game.score > high_score && mode != Mode::Demo {
+                        high_score = game.score;
+                        save_high_score(high_score);
+                    }
+                    if let Some(tl) = timeline.as_mut() {
+                        tl.event(game.tick, "shrink", (hx, hy));
+                    }
+                }
+
+                // check if head is on a boost tile, eating another one
+                // starts the boost over
+                if map.is_tile_boost(hx, hy) {
+                    map.deactivate_tile(hx, hy);
+                    game.boost_until = game.tick + BOOST_TICKS;
+                    if let Some(tl) = timeline.as_mut() {
+                        tl.event(game.tick, "boost", (hx, hy));
+                    }
+                }
+
+                // bonus left uneaten for too long just goes away
+                if let Some((bx, by)) = game.bonus {
+                    if bonus_expired(game.tick - game.bonus_tick, BONUS_LIFETIME_TICKS) {
+                        map.deactivate_tile(bx, by);
+                        game.bonus = None;
+                    }
+                }
+
+                // check if head is on poison tile
+                if map.is_tile_poison(hx, hy) {
+                    map.deactivate_tile(hx, hy);
+                    if let Some(tl) = timeline.as_mut() {
+                        tl.event(game.tick, "poison", (hx, hy));
+                    }
+                    died |= POISON_PENALTY.apply(&mut game.snake);
+                    spawn_poison(
+                        &mut rng,
+                        rows,
+                        cols,
+                        &all_snakes(&game.snake, &game.rival),
+                        &mut map,
+                    );
+                }
+
+                // player two: walls, food and its own tail were dealt with by its
+                // last step like player one's, then poison, obstacles and player one
+                if let Some(r) = game.rival.as_mut() {
+                    r.input(&rival_keys);
+                    let (rx, ry) = r.head_tile();
+                    let rival_outcome = std::mem::take(&mut game.rival_step);
+                    let mut rival_died = rival_outcome.died;
+                    rival_died |= game.obstacles.iter().any(|o| o.cell == (rx, ry));
+
+                    if rival_outcome.ate {
+                        spawn_food(
+                            &mut rng,
+                            rows,
+                            cols,
+                            &[&game.snake, &*r],
+                            &mut map,
+                            &mut game.food,
+                        );
+                        game.rival_score += 1;
+                        if !config.reduced_motion {
+                            burst(&mut particles, (rx, ry));
+                        }
+                        play_sfx(&mut eat_sound);
+                    }
+
+                    if map.is_tile_poison(rx, ry) {
+                        map.deactivate_tile(rx, ry);
+                        rival_died |= POISON_PENALTY.apply(r);
+                        spawn_poison(&mut rng, rows, cols, &[&game.snake, &*r], &mut map);
+                    }
+
+                    rival_died = rival_died || game.snake.occupies(rx, ry);
+                    died = died || r.occupies(hx, hy);
+
+                    // in versus a crash ends the game, no rewinds or lives
+                    if died || rival_died {
+                        play_sfx(&mut death_sound);
+                        if !config.reduced_motion {
+                            shake_clock = Some(Clock::start());
+                        }
+                        game.winner = Some(Winner::from_deaths(died, rival_died));
+                        state = AppState::GameOver;
+                    }
+                }
+
+                if died && game.invulnerable_ticks == 0 && state == AppState::Playing {
+                    play_sfx(&mut death_sound);
+                    if !config.reduced_motion {
+                        shake_clock = Some(Clock::start());
+                    }
+                    if on_death(
+                        &mut game.snake,
+                        &mut game.history,
+                        &mut game.rewind_charges,
+                        &mut timeline,
+                        game.tick,
+                    ) {
+                        if lose_life(&mut game.lives) {
+                            if mode == Mode::Hardcore {
+                                wipe_hardcore_save();
+                            }
+                            state = AppState::GameOver;
+                        } else {
+                            // back to the spawn at the starting length, the score is kept
+                            game.snake =
+                                Snake::with_length(level.spawn, Direction::Right, level.length);
+                            game.history.clear();
+                            game.invulnerable_ticks = INVULNERABLE_TICKS;
+                            game.boost_until = 0;
+                            game.countdown.restart();
+                            state = AppState::Countdown;
+                        }
+                    }
+                }
+
+                if mode == Mode::Timed && game.tick >= game.time_limit {
+                    if let Some(tl) = timeline.as_mut() {
+                        tl.event(game.tick, "time up", (hx, hy));
+                    }
+                    state = AppState::GameOver;
+                }
+
+                let move_due = state == AppState::Playing
+                    && update_snake.take_move(config.turn_based, &mut step_requested, tick_ms);
+                if move_due {
+                    game.history.push(&game.snake);
+                    tick += 1;
+                    game.slow_ticks = game.slow_ticks.saturating_sub(1);
+                    game.invulnerable_ticks = game.invulnerable_ticks.saturating_sub(1);
+
+                    if mode == Mode::Demo {
+                        let dir = demo_direction(&game.snake, &game.food, &map);
+                        game.snake.head.set_direction(dir);
+                    }
+
+                    if AUTO_TURN_ASSIST {
+                        let (hx, hy) = game.snake.head_tile();
+                        if let Some(dir) = auto_turn(&map, hx, hy, game.snake.head.dir) {
+                            game.snake.head.set_direction(dir);
+                        }
+                    }
+
+                    let wrap_size = if config.wrap.unwrap_or(settings.wrap) {
+                        Some((map.width, map.height))
+                    } else {
+                        None
+                    };
+                    let grew = game.snake.grow_pending;
+                    // a replay overrides the player, autopilots and assists
+                    let replayed = playback.as_ref().and_then(|p| p.direction_at(game.tick));
+                    let rival_replayed = playback
+                        .as_ref()
+                        .and_then(|p| p.rival_direction_at(game.tick));
+                    let mut world = GameState {
+                        snake: &mut game.snake,
+                        map: &mut map,
+                        food: &mut game.food,
+                        wrap: wrap_size,
+                        walls_lethal: settings.walls_lethal,
+                    };
+                    game.last_step = step(&mut world, replayed);
+                    game.longest = game.longest.max(game.snake.tail.len() + 1);
+                    let (prev_x, prev_y) = game.last_step.vacated;
+                    if let Some(rec) = game.recording.as_mut() {
+                        rec.record(game.snake.head.dir);
+                    }
+                    if let Some(r) = game.rival.as_mut() {
+                        if mode == Mode::VsComputer {
+                            let dir = ai_choose_direction(r, &[&game.snake], &game.food, &map);
+                            r.head.set_direction(dir);
+                        }
+                        if let Some(dir) = rival_replayed {
+                            r.head.set_direction(dir);
+                        }
+                        if let Some(rec) = game.recording.as_mut() {
+                            rec.record_rival(r.head.dir);
+                        }
+                        let mut world = GameState {
+                            snake: r,
+                            map: &mut map,
+                            food: &mut game.food,
+                            wrap: wrap_size,
+                            walls_lethal: settings.walls_lethal,
+                        };
+                        game.rival_step = step(&mut world, None);
+                    }
+                    game.tick += 1;
+                    for o in game.obstacles.iter_mut() {
+                        o.update(&map);
+                    }
+
+                    // prev_x and prev_y is the tile the snake just left
+                    if SLIME_TRAIL
+                        && trail_slime(&mut map, &game.snake, (prev_x, prev_y), tick, SLIME_TICKS)
+                    {
+                        game.slow_ticks = SLIME_SLOW_TICKS;
+                    }
+
+                    if let Some(tl) = timeline.as_mut() {
+                        tl.record(game.tick, game.snake.head.dir, game.snake.head_tile());
+                    }
+
+                    if grew {
+                        flash_until_ms = flash_clock.elapsed_time().as_milliseconds() + FLASH_MS;
+                        if let Some(tl) = timeline.as_mut() {
+                            tl.event(game.tick, "grow", game.snake.head_tile());
+                        }
+                    }
+
+                    moves += 1;
+                }
+                if !move_due {
+                    break;
+                }
+                if moves == MAX_MOVES_PER_FRAME {
+                    // too far behind after a hitch, drop the rest
+                    update_snake.restart();
                     break;
                 }
             }
+        }
 
-            // update snake every so oftern as to not fly off screen
-            if update_snake.elapsed_time().as_milliseconds() >= 95 {
-                // store last position
-                let mut prev_x = head.get_x();
-                let mut prev_y = head.get_y();
-                head.update();
-
-                for t in tail.iter_mut() {
-                    // store last position
-                    let prev_tx = t.get_x();
-                    let prev_ty = t.get_y();
-                    t.update(prev_x, prev_y);
-                    prev_x = prev_tx;
-                    prev_y = prev_ty;
+        if title_score != Some(game.score) {
+            window.set_title(&format!("{} \u{2014} Score: {}", WINDOW_TITLE, game.score));
+            title_score = Some(game.score);
+        }
+
+        if state == AppState::GameOver || state == AppState::Won {
+            if let Some(rec) = game.recording.take() {
+                if let Err(e) = rec.save(REPLAY_PATH) {
+                    println!("failed to save replay: {}", e);
                 }
+            }
 
-                if add_segment {
-                    // prev_x and prev_y should be last tail seg prev x and y
-                    let new_seg = Tail::new(prev_x, prev_y, BLOCK_SIZE, Color::RED);
-                    tail.push(new_seg);
-                    add_segment = false;
+            // the demo and replays aren't games the player played
+            if !game.score_entered && mode != Mode::Demo && playback.is_none() {
+                stats.record_game(
+                    game.food_eaten,
+                    game.longest as u32,
+                    game.score,
+                    game.played_ms as u64,
+                );
+                if let Err(e) = save_stats(STATS_PATH, &stats) {
+                    println!("failed to save stats: {}", e);
                 }
+            }
 
-                update_snake.restart();
-            }  
+            // versus scores and the demo's don't go on the board
+            let solo = game.rival.is_none() && mode != Mode::Demo && playback.is_none();
+            if !game.score_entered && solo && qualifies(&leaderboard, game.score) {
+                name_entry.clear();
+                // back to this screen once the name is in
+                leaderboard_back = state;
+                state = AppState::NameEntry;
+            }
+            game.score_entered = true;
+        }
 
+        if state == AppState::MapSelect {
+            let mut r = SfmlRenderer::new(&mut window, &font);
+            r.clear(Color::rgb(21, 21, 21));
+            let h = r.view_size().y;
+            draw_centered_text("Choose a map", h / 6.0, 36, &mut r);
+            for (i, source) in maps.iter().enumerate() {
+                let line = if i == map_choice {
+                    format!("> {} <", source.name())
+                } else {
+                    source.name()
+                };
+                let y = h / 6.0 + 60.0 + i as f32 * 28.0;
+                draw_centered_text(&line, y, 22, &mut r);
+            }
+            if let Some(e) = map_error.as_ref() {
+                draw_centered_text(e, h - 50.0, 18, &mut r);
+            }
+            r.display();
+        } else if state == AppState::Menu {
+            let mut r = SfmlRenderer::new(&mut window, &font);
+            r.clear(Color::rgb(21, 21, 21));
+            let h = r.view_size().y;
+            draw_centered_text("RUST SNAKE", h / 3.0, 48, &mut r);
+            draw_centered_text("Press Enter to play", h / 2.0, 24, &mut r);
+            let pick = format!(
+                "Difficulty: {:?}  (0 Slow / 1 Easy / 2 Normal / 3 Hard)",
+                difficulty
+            );
+            draw_centered_text(&pick, h / 2.0 + 40.0, 18, &mut r);
+            let rules = format!("Mode: {:?}  (Tab to change)", mode);
+            draw_centered_text(&rules, h / 2.0 + 70.0, 18, &mut r);
+            draw_centered_text(
+                "L for the leaderboard, I for stats, C for controls",
+                h / 2.0 + 100.0,
+                18,
+                &mut r,
+            );
+            r.display();
+        } else if state == AppState::NameEntry {
+            let mut r = SfmlRenderer::new(&mut window, &font);
+            r.clear(Color::rgb(21, 21, 21));
+            let h = r.view_size().y;
+            let title = format!("New top score: {}", game.score);
+            draw_centered_text(&title, h / 3.0, 36, &mut r);
+            // underscores mark the letters still to type
+            let typed = format!("{:_<1$}", name_entry, NAME_LEN);
+            draw_centered_text(&typed, h / 2.0, 48, &mut r);
+            draw_centered_text(
+                "Type your initials, Enter to save",
+                h / 2.0 + 70.0,
+                18,
+                &mut r,
+            );
+            r.display();
+        } else if state == AppState::Leaderboard {
+            let mut r = SfmlRenderer::new(&mut window, &font);
+            r.clear(Color::rgb(21, 21, 21));
+            let h = r.view_size().y;
+            draw_centered_text("LEADERBOARD", h / 8.0, 36, &mut r);
+            if leaderboard.is_empty() {
+                draw_centered_text("No scores yet", h / 8.0 + 60.0, 22, &mut r);
+            }
+            for (i, (name, points)) in leaderboard.iter().enumerate() {
+                let line = format!("{:>2}. {}  {:>5}", i + 1, name, points);
+                let y = h / 8.0 + 60.0 + i as f32 * 28.0;
+                draw_centered_text(&line, y, 22, &mut r);
+            }
+            draw_centered_text("Press Enter to go back", h - 50.0, 18, &mut r);
+            r.display();
+        } else if state == AppState::Stats {
+            let mut r = SfmlRenderer::new(&mut window, &font);
+            r.clear(Color::rgb(21, 21, 21));
+            let h = r.view_size().y;
+            draw_centered_text("STATS", h / 8.0, 36, &mut r);
+            // hours, a lifetime of play runs past what MM:SS reads well
+            let secs = stats.played_ms / 1000;
+            let played = format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+            let lines = [
+                format!("Games played: {}", stats.games_played),
+                format!("Food eaten: {}", stats.food_eaten),
+                format!("Longest snake: {}", stats.longest_snake),
+                format!("Best score: {}", stats.best_score),
+                format!("Time played: {}", played),
+            ];
+            for (i, line) in lines.iter().enumerate() {
+                let y = h / 8.0 + 60.0 + i as f32 * 28.0;
+                draw_centered_text(line, y, 22, &mut r);
+            }
+            draw_centered_text("Press Enter to go back", h - 50.0, 18, &mut r);
+            r.display();
+        } else if state == AppState::Controls {
+            let mut r = SfmlRenderer::new(&mut window, &font);
+            r.clear(Color::rgb(21, 21, 21));
+            let h = r.view_size().y;
+            draw_centered_text("CONTROLS", h / 8.0, 36, &mut r);
+            if let Some(action) = remap.action() {
+                let ask = format!("Press a key for {:?}", action);
+                draw_centered_text(&ask, h / 2.0, 28, &mut r);
+            }
+            if let Some(message) = remap.message.as_ref() {
+                draw_centered_text(message, h / 2.0 + 50.0, 18, &mut r);
+            }
+            draw_centered_text("Escape to keep the old keys", h - 50.0, 18, &mut r);
+            r.display();
+        } else {
             // --------------------------
             // render
             // --------------------------
-            window.clear(Color::WHITE);
-            map.draw(&mut window);
-            head.draw(&mut window);
-            for t in tail.iter_mut() {
-                t.draw(&mut window);
+            // shaking moves the view for this frame only, it's put back after
+            // how far through the current move, 1.0 draws every piece on its cell
+            let moving = state == AppState::Playing || state == AppState::Paused;
+            let progress = if config.smooth_movement && !config.turn_based && moving {
+                render_offset(update_snake.elapsed_ms(), tick_ms)
+            } else {
+                1.0
+            };
+
+            // shaking and the camera move the views for this frame only, the
+            // window's own view is put back after
+            let still_view = window.view().to_owned();
+            let mut hud_view = still_view.to_owned();
+            if let Some(c) = shake_clock.as_ref() {
+                let elapsed = c.elapsed_time().as_milliseconds();
+                if elapsed >= SHAKE_MS {
+                    shake_clock = None;
+                } else {
+                    hud_view.move_(shake_offset(elapsed, SHAKE_MS, SHAKE_PX));
+                }
+            }
+            let mut map_view = hud_view.to_owned();
+            if config.follow_camera {
+                let (x, y) = game.snake.head.screen_pos(progress);
+                let head = Vector2f::new(x + TILE_W / 2.0, y + TILE_H / 2.0);
+                let map_size = Vector2f::new(map.width as f32 * TILE_W, map.height as f32 * TILE_H);
+                let shake = hud_view.center() - still_view.center();
+                map_view.set_center(clamp_camera(head, still_view.size(), map_size) + shake);
+            }
+            window.set_view(&map_view);
+            let mut renderer = SfmlRenderer::new(&mut window, &font);
+            renderer.clear(theme.background);
+            map.draw(&theme, &mut renderer);
+            if SLIME_TRAIL {
+                map.draw_slime(tick, &mut renderer);
+            }
+            if show_grid {
+                map.draw_grid(&mut renderer);
             }
-            window.display();
+            for o in game.obstacles.iter() {
+                o.draw(&theme, &mut renderer);
+            }
+            let mut snake_theme = theme;
+            if WARP_FINALE && !config.reduced_motion {
+                let intensity = finale_intensity(game.score, high_score, WARP_MARGIN);
+                let seconds = finale_clock.elapsed_time().as_seconds();
+                snake_theme.head = finale_color(theme.head, intensity, seconds);
+            }
+            // only mid game, a flash still going when the run ends is dropped
+            let flashing = flash_clock.elapsed_time().as_milliseconds() < flash_until_ms;
+            if flashing && state == AppState::Playing && !config.reduced_motion {
+                snake_theme.head = lerp_color(snake_theme.head, Color::WHITE, FLASH_AMOUNT);
+                snake_theme.tail = lerp_color(snake_theme.tail, Color::WHITE, FLASH_AMOUNT);
+            }
+            // blink while invulnerable, or a steady pale tint with reduced motion
+            if game.invulnerable_ticks > 0 && config.reduced_motion {
+                snake_theme.head = lerp_color(snake_theme.head, Color::WHITE, INVULNERABLE_TINT);
+                snake_theme.tail = lerp_color(snake_theme.tail, Color::WHITE, INVULNERABLE_TINT);
+                game.snake.draw(&snake_theme, progress, &mut renderer);
+            } else {
+                game.snake.draw_blinking(
+                    &snake_theme,
+                    progress,
+                    game.invulnerable_ticks,
+                    state == AppState::Playing,
+                    &mut renderer,
+                );
+            }
+            if let Some(r) = game.rival.as_ref() {
+                let mut rival_theme = theme;
+                rival_theme.head = theme.rival_head;
+                rival_theme.tail = theme.rival_tail;
+                r.draw(&rival_theme, progress, &mut renderer);
+            }
+            if state != AppState::Paused {
+                update_particles(&mut particles, frame_ms as f32);
+            }
+            for p in particles.iter() {
+                p.draw(theme.food, &mut renderer);
+            }
+            renderer.window().set_view(&hud_view);
+            if config.follow_camera && show_minimap {
+                let h = MINIMAP_WIDTH * map.height as f32 / map.width.max(1) as f32;
+                let corner = FloatRect::new(
+                    width as f32 - MINIMAP_WIDTH - 8.0,
+                    height as f32 - h - 8.0,
+                    MINIMAP_WIDTH,
+                    h,
+                );
+                let snakes = all_snakes(&game.snake, &game.rival);
+                draw_minimap(&mut renderer, &map, &snakes, &game.food, corner, &theme);
+            }
+            draw_rewind_charges(game.rewind_charges, width as f32, &mut renderer);
+            let speed = format!("{:.1} tiles/s", tiles_per_second(tick_ms));
+            draw_hud_text(&format!("score: {}", game.score), 4.0, 2.0, &mut renderer);
+            let best = format!("best: {}", high_score);
+            draw_hud_text(&best, 120.0, 2.0, &mut renderer);
+            draw_hud_text(&format!("lives: {}", game.lives), 240.0, 2.0, &mut renderer);
+            if game.rival.is_some() {
+                let p2 = format!("p2: {}", game.rival_score);
+                draw_hud_text(&p2, 460.0, 2.0, &mut renderer);
+            }
+            if mode == Mode::Timed {
+                let ticks_left = game.time_limit.saturating_sub(game.tick) as i32;
+                let left = format_mm_ss(ticks_left * tick_ms);
+                draw_hud_text(&format!("time: {}", left), 340.0, 2.0, &mut renderer);
+            }
+            draw_hud_text(&speed, 4.0, 22.0, &mut renderer);
+            if game.multiplier > 1 {
+                let combo = format!("combo x{}", game.multiplier);
+                draw_hud_text(&combo, 120.0, 22.0, &mut renderer);
+            }
+            if show_fps {
+                let y = height as f32 - 22.0;
+                draw_hud_text(&format!("{:.0} fps", fps), 4.0, y, &mut renderer);
+            }
+
+            if state == AppState::GameOver {
+                let y = height as f32 / 2.0 - 20.0;
+                let title = if mode == Mode::Hardcore {
+                    "Game Over - press Enter for the menu"
+                } else {
+                    "Game Over - press R to restart"
+                };
+                draw_centered_text(title, y, 32, &mut renderer);
+                if let Some(w) = game.winner {
+                    let result = match w {
+                        Winner::PlayerOne => "Player 1 wins",
+                        Winner::PlayerTwo => "Player 2 wins",
+                        Winner::Draw => "Draw",
+                    };
+                    draw_centered_text(result, y + 44.0, 24, &mut renderer);
+                } else {
+                    let run = format!(
+                        "Score: {}  Time: {}",
+                        game.score,
+                        format_mm_ss(game.played_ms)
+                    );
+                    draw_centered_text(&run, y + 44.0, 24, &mut renderer);
+                }
+            }
+            if state == AppState::Won {
+                let y = height as f32 / 2.0 - 20.0;
+                draw_centered_text("You Win! - press R to play again", y, 32, &mut renderer);
+                let total = format!(
+                    "Final score: {}  Time: {}",
+                    game.score,
+                    format_mm_ss(game.played_ms)
+                );
+                draw_centered_text(&total, y + 44.0, 24, &mut renderer);
+            }
+            if state == AppState::Paused {
+                let y = height as f32 / 2.0 - 20.0;
+                draw_centered_text("PAUSED", y, 32, &mut renderer);
+            }
+            if state == AppState::Countdown || state == AppState::Playing {
+                if let Some(label) =
+                    countdown_label(game.countdown.elapsed_time().as_milliseconds())
+                {
+                    let y = height as f32 / 2.0 - 40.0;
+                    draw_centered_text(&label, y, 64, &mut renderer);
+                }
+            }
+            renderer.display();
+            window.set_view(&still_view);
         }
     }
+
+    Ok(())
 }
 
+const USAGE: &str = "usage: rust_snake [--width <px>] [--height <px>] [--fps <n>] \
+[--map <path>] [--seed <n>] [--replay <path>] [--trace]";
+
 fn main() {
-    run(SCREEN_WIDTH, SCREEN_HEIGHT);
+    let args: Vec<String> = std::env::args().collect();
+    // a missing file is fine, a broken one is worth a mention
+    let mut config = match load_config(CONFIG_PATH) {
+        Ok(config) => config,
+        Err(e) if e.kind() == ErrorKind::NotFound => Config::default(),
+        Err(e) => {
+            println!("ignoring config: {}", e);
+            Config::default()
+        }
+    };
+    // the command line wins over the config file
+    let (width, height) = match parse_window_size(&args, (config.width, config.height)) {
+        Ok(size) => size,
+        Err(e) => {
+            eprintln!("rust snake: {}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let framerate = match parse_framerate(&args, config.framerate) {
+        Ok(fps) => fps,
+        Err(e) => {
+            eprintln!("rust snake: {}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let map_path = match flag_value(&args, "--map") {
+        Some("") => {
+            eprintln!("rust snake: --map needs a file\n{}", USAGE);
+            std::process::exit(2);
+        }
+        path => path.map(String::from),
+    };
+    let trace = args.iter().any(|a| a == "--trace");
+    // --seed <n> replays the same food / poison sequence
+    let seed = match flag_value(&args, "--seed").map(str::parse::<u64>) {
+        None => None,
+        Some(Ok(seed)) => Some(seed),
+        Some(Err(_)) => {
+            eprintln!("rust snake: --seed needs a whole number\n{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    // --replay <path> plays back a game saved to REPLAY_PATH
+    let playback = match args.iter().position(|a| a == "--replay") {
+        Some(i) => match args.get(i + 1).map(|p| Replay::load(p)) {
+            Some(Ok(replay)) => Some(replay),
+            Some(Err(e)) => {
+                eprintln!("rust snake: failed to load replay: {}", e);
+                std::process::exit(1);
+            }
+            None => {
+                eprintln!("rust snake: --replay needs a file");
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    config.width = width;
+    config.height = height;
+    config.framerate = framerate;
+    if let Err(e) = run(config, map_path, trace, seed, playback) {
+        eprintln!("rust snake: {}", e);
+        std::process::exit(1);
+    }
 }
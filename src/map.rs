@@ -0,0 +1,1167 @@
+//! map tiles, the map grid and loading maps from file
+
+use crate::render::{push_rect, Renderer};
+use crate::theme::Theme;
+use crate::{SHUFFLE_ATTEMPTS, TILE_H, TILE_W};
+use sfml::graphics::{Color, Vertex};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Result};
+
+// -----------------------------------
+// CONSTS
+// -----------------------------------
+/// vertices each tile takes in the map's batch, two quads
+const TILE_VERTICES: usize = 8;
+
+// -----------------------------------
+// ENUMS
+// -----------------------------------
+#[derive(Debug, PartialEq, Clone)]
+pub enum TileType {
+    Blocked,
+    NonBlocked,
+    Active,
+    NonActive,
+    Poison,
+    Obstacle,
+    Bonus,
+    Shrink,
+    /// speeds the snake up for a while
+    Boost,
+    /// one end of a portal pair, both ends share the id
+    Portal(u8),
+}
+
+// -----------------------------------
+// TILE
+// -----------------------------------
+/// one cell of the map, every tile is TILE_W x TILE_H px so only its type
+/// is stored
+#[derive(Clone)]
+pub struct Tile {
+    tile_type: TileType,
+}
+
+impl Tile {
+    pub fn new(tile: TileType) -> Self {
+        Self { tile_type: tile }
+    }
+
+    /// corners of this tile at col x, row y for `Renderer::draw_quads`.
+    /// always two quads so every tile takes the same room in the map's
+    /// batch, only walls need the second and the rest leave it empty
+    pub fn quads(&self, x: f32, y: f32, theme: &Theme) -> Vec<Vertex> {
+        let mut quads = Vec::with_capacity(TILE_VERTICES);
+        let (w, h) = (TILE_W, TILE_H);
+        let pos_x = x * w;
+        let pos_y = y * h;
+
+        let col = match self.tile_type {
+            TileType::Active => theme.food,
+            TileType::Blocked => theme.wall,
+            TileType::Obstacle => theme.obstacle,
+            TileType::Poison => theme.poison,
+            TileType::Bonus => theme.bonus,
+            TileType::Shrink => theme.shrink,
+            TileType::Boost => theme.boost,
+            TileType::Portal(_) => theme.portal,
+            TileType::NonBlocked | TileType::NonActive => theme.floor,
+        };
+
+        if self.tile_type == TileType::Blocked {
+            // the edge color fills the tile and the wall sits inset on top
+            let edge = theme.wall_edge_px.min(w / 2.0).min(h / 2.0);
+            push_rect(&mut quads, pos_x, pos_y, w, h, theme.wall_edge);
+            push_rect(
+                &mut quads,
+                pos_x + edge,
+                pos_y + edge,
+                w - 2.0 * edge,
+                h - 2.0 * edge,
+                col,
+            );
+        } else {
+            push_rect(&mut quads, pos_x, pos_y, w, h, col);
+            push_rect(&mut quads, pos_x, pos_y, 0.0, 0.0, col);
+        }
+        quads
+    }
+}
+
+// -----------------------------------
+// ERRORS
+// -----------------------------------
+/// a character a map file can't contain, `line` and `col` count from 1.
+/// map loading returns it inside an InvalidData io::Error, get it back
+/// with `get_ref` and `downcast_ref`
+#[derive(Debug, PartialEq)]
+pub struct MapParseError {
+    pub line: usize,
+    pub col: usize,
+    pub found: char,
+}
+
+impl fmt::Display for MapParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unexpected {:?} at line {}, column {}",
+            self.found, self.line, self.col
+        )
+    }
+}
+
+impl std::error::Error for MapParseError {}
+
+// -----------------------------------
+// MAP
+// -----------------------------------
+#[allow(dead_code)]
+pub struct Map {
+    tiles: Vec<Tile>,
+    pub width: i32,
+    pub height: i32,
+    /// tick each tile stays slimed until, 0 for none
+    slime: Vec<u64>,
+    /// indices of tiles changed since the last draw
+    dirty: Vec<usize>,
+    /// every tile's quads, kept between draws and only rebuilt where dirty
+    quads: Vec<Vertex>,
+    /// theme `quads` were built with, None before the first draw
+    quads_theme: Option<Theme>,
+}
+
+impl Map {
+    pub fn new(width: i32, height: i32, map_data: Vec<Tile>) -> Self {
+        let slime = vec![0; map_data.len()];
+        Self {
+            tiles: map_data,
+            width,
+            height,
+            slime,
+            dirty: Vec::new(),
+            quads: Vec::new(),
+            quads_theme: None,
+        }
+    }
+
+    /// set the tile at index `i`, marking it for the next draw if it changed
+    fn set_tile_type(&mut self, i: usize, tile_type: TileType) {
+        if let Some(t) = self.tiles.get_mut(i) {
+            if t.tile_type != tile_type {
+                t.tile_type = tile_type;
+                if !self.dirty.contains(&i) {
+                    self.dirty.push(i);
+                }
+            }
+        }
+    }
+
+    fn set_tile_at(&mut self, x: i32, y: i32, tile_type: TileType) {
+        if let Some(i) = self.index(x, y) {
+            self.set_tile_type(i, tile_type);
+        }
+    }
+
+    /// index into `tiles` of x, y, None off the map. checked per axis, an x
+    /// past the right edge would otherwise land on the next row
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+        Some((x + self.width * y) as usize)
+    }
+
+    /// is x, y a cell of the map
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    /// get tile row/column coord from screen coord
+    pub fn get_tile_coord(&self, x: i32, y: i32) -> (i32, i32) {
+        let cx = x / TILE_W as i32;
+        let cy = y / TILE_H as i32;
+        (cx, cy)
+    }
+
+    pub fn is_tile_active(&self, x: i32, y: i32) -> bool {
+        if let Some(t) = self.index(x, y).and_then(|i| self.tiles.get(i)) {
+            if t.tile_type == TileType::Active {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn is_tile_blocked(&self, x: i32, y: i32) -> bool {
+        if let Some(t) = self.index(x, y).and_then(|i| self.tiles.get(i)) {
+            if t.tile_type == TileType::Blocked || t.tile_type == TileType::Obstacle {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn is_tile_poison(&self, x: i32, y: i32) -> bool {
+        if let Some(t) = self.index(x, y).and_then(|i| self.tiles.get(i)) {
+            if t.tile_type == TileType::Poison {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn is_tile_bonus(&self, x: i32, y: i32) -> bool {
+        if let Some(t) = self.index(x, y).and_then(|i| self.tiles.get(i)) {
+            if t.tile_type == TileType::Bonus {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn is_tile_shrink(&self, x: i32, y: i32) -> bool {
+        if let Some(t) = self.index(x, y).and_then(|i| self.tiles.get(i)) {
+            if t.tile_type == TileType::Shrink {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn is_tile_boost(&self, x: i32, y: i32) -> bool {
+        if let Some(t) = self.index(x, y).and_then(|i| self.tiles.get(i)) {
+            if t.tile_type == TileType::Boost {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// id of the portal at x, y, None if there isn't one
+    pub fn portal_at(&self, x: i32, y: i32) -> Option<u8> {
+        match self.tiles.get(self.index(x, y)?)?.tile_type {
+            TileType::Portal(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// cell at the other end of the portal at x, y
+    pub fn portal_exit(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let id = self.portal_at(x, y)?;
+        self.tiles_of(TileType::Portal(id))
+            .into_iter()
+            .find(|&cell| cell != (x, y))
+    }
+
+    /// x, y of every tile of a given type
+    pub fn tiles_of(&self, tile_type: TileType) -> Vec<(i32, i32)> {
+        (0..self.tiles.len() as i32)
+            .filter(|&i| self.tiles[i as usize].tile_type == tile_type)
+            .map(|i| (i % self.width, i / self.width))
+            .collect()
+    }
+
+    /// number of tiles of a given type currently on the map
+    pub fn count_tiles(&self, tile_type: TileType) -> usize {
+        self.tiles
+            .iter()
+            .filter(|t| t.tile_type == tile_type)
+            .count()
+    }
+
+    pub fn activate_tile(&mut self, x: i32, y: i32) {
+        self.set_tile_at(x, y, TileType::Active);
+    }
+
+    pub fn poison_tile(&mut self, x: i32, y: i32) {
+        self.set_tile_at(x, y, TileType::Poison);
+    }
+
+    pub fn bonus_tile(&mut self, x: i32, y: i32) {
+        self.set_tile_at(x, y, TileType::Bonus);
+    }
+
+    pub fn shrink_tile(&mut self, x: i32, y: i32) {
+        self.set_tile_at(x, y, TileType::Shrink);
+    }
+
+    pub fn boost_tile(&mut self, x: i32, y: i32) {
+        self.set_tile_at(x, y, TileType::Boost);
+    }
+
+    pub fn deactivate_tile(&mut self, x: i32, y: i32) {
+        self.set_tile_at(x, y, TileType::NonActive);
+    }
+
+    pub fn block_tile(&mut self, x: i32, y: i32) {
+        self.set_tile_at(x, y, TileType::Blocked);
+    }
+
+    /// turn blocked and obstacle tiles within `radius` tiles of spawn into floor
+    pub fn clear_spawn_zone(&mut self, spawn: (i32, i32), radius: i32) {
+        let (sx, sy) = spawn;
+        for y in (sy - radius)..=(sy + radius) {
+            for x in (sx - radius)..=(sx + radius) {
+                if self.is_tile_blocked(x, y) {
+                    self.set_tile_at(x, y, TileType::NonBlocked);
+                }
+            }
+        }
+    }
+
+    /// closest in bounds tile to col, row that isn't a wall or obstacle
+    pub fn nearest_open_cell(&self, col: i32, row: i32) -> Option<(i32, i32)> {
+        self.nearest_cell_where(col, row, |x, y| !self.is_tile_blocked(x, y))
+    }
+
+    /// closest in bounds tile to col, row (by steps, breadth first) for which
+    /// `ok` returns true, col, row itself included
+    pub fn nearest_cell_where<F: Fn(i32, i32) -> bool>(
+        &self,
+        col: i32,
+        row: i32,
+        ok: F,
+    ) -> Option<(i32, i32)> {
+        if !self.in_bounds(col, row) {
+            return None;
+        }
+
+        let mut seen = vec![false; (self.width * self.height) as usize];
+        let mut queue = std::collections::VecDeque::new();
+        seen[(col + self.width * row) as usize] = true;
+        queue.push_back((col, row));
+
+        while let Some((x, y)) = queue.pop_front() {
+            if ok(x, y) {
+                return Some((x, y));
+            }
+
+            for (dx, dy) in [(0, -1), (0, 1), (-1, 0), (1, 0)].iter() {
+                let (nx, ny) = (x + dx, y + dy);
+                if !self.in_bounds(nx, ny) {
+                    continue;
+                }
+                let n = (nx + self.width * ny) as usize;
+                if !seen[n] {
+                    seen[n] = true;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// true if every non blocked tile can reach every other one
+    pub fn is_connected(&self) -> bool {
+        let open = |t: &Tile| t.tile_type != TileType::Blocked && t.tile_type != TileType::Obstacle;
+
+        let start = match self.tiles.iter().position(open) {
+            Some(i) => i,
+            None => return true,
+        };
+
+        let mut seen = vec![false; self.tiles.len()];
+        let mut stack = vec![start];
+        let mut reached = 0;
+        seen[start] = true;
+
+        while let Some(i) = stack.pop() {
+            reached += 1;
+            let x = i as i32 % self.width;
+            let y = i as i32 / self.width;
+
+            for (dx, dy) in [(0, -1), (0, 1), (-1, 0), (1, 0)].iter() {
+                let (nx, ny) = (x + dx, y + dy);
+                let n = match self.index(nx, ny) {
+                    Some(n) => n,
+                    None => continue,
+                };
+                if let Some(t) = self.tiles.get(n) {
+                    if !seen[n] && open(t) {
+                        seen[n] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+
+        reached == self.tiles.iter().filter(|t| open(t)).count()
+    }
+
+    /// move every obstacle tile to a random free tile, keeping the map
+    /// connected and the tiles in `keep_clear` free. walls stay put.
+    pub fn shuffle_obstacles<R: rand::Rng>(&mut self, rng: &mut R, keep_clear: &[(i32, i32)]) {
+        let original: Vec<usize> = (0..self.tiles.len())
+            .filter(|&i| self.tiles[i].tile_type == TileType::Obstacle)
+            .collect();
+        if original.is_empty() {
+            return;
+        }
+
+        let clear: Vec<usize> = keep_clear
+            .iter()
+            .filter_map(|&(x, y)| self.index(x, y))
+            .collect();
+
+        for &i in original.iter() {
+            self.set_tile_type(i, TileType::NonBlocked);
+        }
+
+        for _ in 0..SHUFFLE_ATTEMPTS {
+            let free: Vec<usize> = (0..self.tiles.len())
+                .filter(|i| !clear.contains(i))
+                .filter(|&i| {
+                    let t = &self.tiles[i].tile_type;
+                    *t == TileType::NonBlocked || *t == TileType::NonActive
+                })
+                .collect();
+            if free.len() < original.len() {
+                break;
+            }
+
+            let picked: Vec<usize> = rand::seq::index::sample(rng, free.len(), original.len())
+                .into_iter()
+                .map(|i| free[i])
+                .collect();
+            for &i in picked.iter() {
+                self.set_tile_type(i, TileType::Obstacle);
+            }
+
+            if self.is_connected() {
+                return;
+            }
+
+            for &i in picked.iter() {
+                self.set_tile_type(i, TileType::NonBlocked);
+            }
+        }
+
+        // no connected layout found, put them back where they were
+        for &i in original.iter() {
+            self.set_tile_type(i, TileType::Obstacle);
+        }
+    }
+
+    /// slime a tile until `until_tick`
+    pub fn slime_tile(&mut self, x: i32, y: i32, until_tick: u64) {
+        if let Some(s) = self.index(x, y).and_then(|i| self.slime.get_mut(i)) {
+            *s = until_tick;
+        }
+    }
+
+    pub fn is_tile_slimed(&self, x: i32, y: i32, tick: u64) -> bool {
+        if let Some(s) = self.index(x, y).and_then(|i| self.slime.get(i)) {
+            return *s > tick;
+        }
+        false
+    }
+
+    /// draw thin lines between every tile
+    pub fn draw_grid(&self, r: &mut dyn Renderer) {
+        let w = self.width as f32 * TILE_W;
+        let h = self.height as f32 * TILE_H;
+        let color = Color::rgba(255, 255, 255, 30);
+
+        for x in 0..=self.width {
+            r.draw_rect(x as f32 * TILE_W, 0.0, 1.0, h, color);
+        }
+        for y in 0..=self.height {
+            r.draw_rect(0.0, y as f32 * TILE_H, w, 1.0, color);
+        }
+    }
+
+    /// draw a faint overlay over tiles still slimed at `tick`
+    pub fn draw_slime(&self, tick: u64, r: &mut dyn Renderer) {
+        for (i, s) in self.slime.iter().enumerate() {
+            if *s <= tick {
+                continue;
+            }
+            let x = i as i32 % self.width;
+            let y = i as i32 / self.width;
+            r.draw_cell(x as f32, y as f32, Color::rgba(120, 255, 120, 40));
+        }
+    }
+
+    /// every tile in one draw call instead of one per tile, 1 a frame where a
+    /// 32x24 map took 768 (counted by a test). the batch is kept
+    /// between frames, only tiles changed since the last draw are rebuilt, or
+    /// all of them when the theme changes
+    pub fn draw(&mut self, theme: &Theme, r: &mut dyn Renderer) {
+        let width = self.width;
+        let cell = |i: usize| ((i as i32 % width) as f32, (i as i32 / width) as f32);
+
+        if self.quads_theme != Some(*theme) {
+            self.quads.clear();
+            // draw 1d array as a 2d array
+            for (i, t) in self.tiles.iter().enumerate() {
+                let (x, y) = cell(i);
+                self.quads.extend(t.quads(x, y, theme));
+            }
+            self.quads_theme = Some(*theme);
+            self.dirty.clear();
+        }
+
+        for i in self.dirty.drain(..) {
+            let (x, y) = cell(i);
+            let start = i * TILE_VERTICES;
+            self.quads[start..start + TILE_VERTICES]
+                .copy_from_slice(&self.tiles[i].quads(x, y, theme));
+        }
+        r.draw_quads(&self.quads);
+    }
+}
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// (cols, rows) of TILE_W x TILE_H tiles covering a `width` x `height` px window
+pub fn grid_size(width: u32, height: u32) -> (i32, i32) {
+    let (tile_w, tile_h) = (TILE_W as u32, TILE_H as u32);
+    debug_assert!(
+        width.is_multiple_of(tile_w),
+        "window width {} is not whole tiles",
+        width
+    );
+    debug_assert!(
+        height.is_multiple_of(tile_h),
+        "window height {} is not whole tiles",
+        height
+    );
+    ((width / tile_w) as i32, (height / tile_h) as i32)
+}
+
+/// extra things a map file marks besides its tiles
+#[derive(Default, Debug)]
+pub struct MapInfo {
+    /// where the snake starts, `S` in the file
+    pub start: Option<(i32, i32)>,
+    /// the two ends of each portal
+    pub portals: Vec<((i32, i32), (i32, i32))>,
+    /// cells bonus tiles spawn on, `B` in the file
+    pub bonus_zones: Vec<(i32, i32)>,
+}
+
+/// load a map file as (tiles, width, height, info), every row must be the
+/// same width. one character per tile:
+///
+/// - `0` floor, `1` wall, `2` food, `3` obstacle
+/// - `a` to `z` portals, the two tiles with the same letter are linked
+/// - `P` portals paired in reading order, the first with the second ...
+/// - `S` floor the snake starts on, at most one
+/// - `B` floor a bonus tile can spawn on
+///
+/// spaces and tabs are ignored, anything else is an error
+pub fn load_from_file(path: &str) -> Result<(Vec<Tile>, i32, i32, MapInfo)> {
+    let (width, height, cells, mut info) = read_cells(path, false)?;
+
+    let types: Vec<TileType> = cells.into_iter().flatten().collect();
+    check_portals(path, &types)?;
+    info.portals = portal_pairs(&types, width);
+    let tiles = types.into_iter().map(Tile::new).collect();
+    Ok((tiles, width, height, info))
+}
+
+/// the cells of a map file as (width, height, cells, info), read as
+/// `load_from_file` describes. with `layer` a `.` is a cell left empty
+fn read_cells(path: &str, layer: bool) -> Result<(i32, i32, Vec<Option<TileType>>, MapInfo)> {
+    let mut cells = Vec::new();
+    let mut info = MapInfo::default();
+    let mut width = 0;
+    let mut height = 0;
+    // `P` ends read so far, ids carry on after the letters
+    let mut unlettered_portals: u8 = 0;
+
+    let file = File::open(path)?;
+    let buffer = BufReader::new(file);
+    let bad_data = |msg: String| Error::new(ErrorKind::InvalidData, format!("{}: {}", path, msg));
+
+    for (line_no, line) in buffer.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut count = 0;
+        for (col, x) in line.chars().enumerate() {
+            let cell = (count, height);
+            let tile_type = match x {
+                '0' => TileType::NonBlocked,
+                '1' => TileType::Blocked,
+                '2' => TileType::Active,
+                '3' => TileType::Obstacle,
+                'a'..='z' => TileType::Portal(x as u8 - b'a'),
+                'P' => {
+                    let id = 26 + unlettered_portals / 2;
+                    unlettered_portals = unlettered_portals.checked_add(1).ok_or_else(|| {
+                        bad_data(format!("too many P portals on line {}", line_no + 1))
+                    })?;
+                    TileType::Portal(id)
+                }
+                'S' => {
+                    if info.start.is_some() {
+                        return Err(bad_data(format!("second S on line {}", line_no + 1)));
+                    }
+                    info.start = Some(cell);
+                    TileType::NonBlocked
+                }
+                'B' => {
+                    info.bonus_zones.push(cell);
+                    TileType::NonBlocked
+                }
+                '.' if layer => {
+                    cells.push(None);
+                    count += 1;
+                    continue;
+                }
+                ' ' | '\t' => continue,
+                found => {
+                    let err = MapParseError {
+                        line: line_no + 1,
+                        col: col + 1,
+                        found,
+                    };
+                    return Err(Error::new(ErrorKind::InvalidData, err));
+                }
+            };
+            cells.push(Some(tile_type));
+            count += 1;
+        }
+
+        if height == 0 {
+            width = count;
+        } else if count != width {
+            return Err(bad_data(format!(
+                "line {} is {} wide, expected {}",
+                line_no + 1,
+                count,
+                width
+            )));
+        }
+        height += 1;
+    }
+
+    Ok((width, height, cells, info))
+}
+
+/// (first end, second end) of each portal, in id order
+fn portal_pairs(tiles: &[TileType], width: i32) -> Vec<((i32, i32), (i32, i32))> {
+    let mut ends: Vec<Vec<(i32, i32)>> = vec![vec![]; 256];
+    for (i, t) in tiles.iter().enumerate() {
+        if let TileType::Portal(id) = t {
+            let i = i as i32;
+            ends[*id as usize].push((i % width, i / width));
+        }
+    }
+    ends.into_iter()
+        .filter(|e| e.len() == 2)
+        .map(|e| (e[0], e[1]))
+        .collect()
+}
+
+/// every portal letter must be used exactly twice, once for each end,
+/// and there must be an even number of `P`s
+fn check_portals(path: &str, tiles: &[TileType]) -> Result<()> {
+    let mut ends = [0; 256];
+    for t in tiles.iter() {
+        if let TileType::Portal(id) = t {
+            ends[*id as usize] += 1;
+        }
+    }
+
+    for (id, &count) in ends.iter().enumerate() {
+        if count != 0 && count != 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{}: portal '{}' has {} ends, expected 2",
+                    path,
+                    if id < 26 {
+                        (b'a' + id as u8) as char
+                    } else {
+                        'P'
+                    },
+                    count
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// paths of every *.txt file in `dir`, sorted by name
+pub fn list_maps(dir: &str) -> Result<Vec<String>> {
+    let mut maps = vec![];
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|e| e == "txt") {
+            maps.push(path.to_string_lossy().into_owned());
+        }
+    }
+
+    maps.sort();
+    Ok(maps)
+}
+
+/// open floor walled in on all four sides
+pub fn blank_map(width: i32, height: i32) -> Vec<Tile> {
+    let mut tiles = Vec::with_capacity((width * height).max(0) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+            let tile_type = if border {
+                TileType::Blocked
+            } else {
+                TileType::NonBlocked
+            };
+            tiles.push(Tile::new(tile_type));
+        }
+    }
+
+    tiles
+}
+
+/// walled border with interior walls scattered at `wall_density` (0..1),
+/// `start` and the tiles around it are always left open
+pub fn generate_map<R: rand::Rng>(
+    rng: &mut R,
+    width: i32,
+    height: i32,
+    wall_density: f32,
+    start: (i32, i32),
+) -> Vec<Tile> {
+    let mut tiles = Vec::with_capacity((width * height).max(0) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+            let near_start = (x - start.0).abs() <= 1 && (y - start.1).abs() <= 1;
+            let tile_type = if border || (!near_start && rng.gen::<f32>() < wall_density) {
+                TileType::Blocked
+            } else {
+                TileType::NonBlocked
+            };
+            tiles.push(Tile::new(tile_type));
+        }
+    }
+
+    tiles
+}
+
+/// read one map layer as (width, height, tiles, info), the same characters
+/// as a map file plus '.' for a tile the layer leaves empty
+pub fn load_layer(path: &str) -> Result<(i32, i32, Vec<Option<TileType>>, MapInfo)> {
+    read_cells(path, true)
+}
+
+/// load a base layer (walls / floor) and an object layer (food ...)
+/// of the same size, object tiles win where both are set. an `S` in
+/// either layer is the start, the object layer's if both have one
+pub fn load_layered(base: &str, objects: &str) -> Result<(Map, MapInfo)> {
+    let (base_w, base_h, base_tiles, base_info) = load_layer(base)?;
+    let (obj_w, obj_h, obj_tiles, obj_info) = load_layer(objects)?;
+
+    if base_w != obj_w || base_h != obj_h {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "layer size mismatch: {} is {}x{}, {} is {}x{}",
+                base, base_w, base_h, objects, obj_w, obj_h
+            ),
+        ));
+    }
+
+    let types: Vec<TileType> = base_tiles
+        .into_iter()
+        .zip(obj_tiles)
+        .map(|(b, o)| o.or(b).unwrap_or(TileType::NonBlocked))
+        .collect();
+    check_portals(objects, &types)?;
+
+    let mut bonus_zones = base_info.bonus_zones;
+    bonus_zones.extend(obj_info.bonus_zones);
+    let info = MapInfo {
+        start: obj_info.start.or(base_info.start),
+        portals: portal_pairs(&types, base_w),
+        bonus_zones,
+    };
+    let tiles = types.into_iter().map(Tile::new).collect();
+
+    Ok((Map::new(base_w, base_h, tiles), info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::mock::{Drawn, MockRenderer};
+    use crate::theme::ThemeName;
+
+    /// 6 wide and 3 tall so a mixed up x and y lands on the wrong cell
+    fn wide_map() -> Map {
+        Map::new(6, 3, blank_map(6, 3))
+    }
+
+    /// `text` written to a file of its own in the temp dir, its path
+    fn map_file(name: &str, text: &str) -> String {
+        let path = std::env::temp_dir().join(format!("rust_snake_test_{}.txt", name));
+        std::fs::write(&path, text).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn in_bounds_edges() {
+        let map = wide_map();
+        assert!(map.in_bounds(0, 0));
+        assert!(map.in_bounds(5, 2));
+        assert!(!map.in_bounds(-1, 0));
+        assert!(!map.in_bounds(0, -1));
+        assert!(!map.in_bounds(6, 0));
+        assert!(!map.in_bounds(0, 3));
+        // a square map's height would let this through
+        assert!(!map.in_bounds(2, 5));
+    }
+
+    #[test]
+    fn tile_coord_from_px() {
+        let map = wide_map();
+        let (w, h) = (TILE_W as i32, TILE_H as i32);
+        assert_eq!(map.get_tile_coord(0, 0), (0, 0));
+        assert_eq!(map.get_tile_coord(w - 1, h - 1), (0, 0));
+        assert_eq!(map.get_tile_coord(w, h), (1, 1));
+        assert_eq!(map.get_tile_coord(5 * w + 3, 2 * h + 3), (5, 2));
+    }
+
+    #[test]
+    fn activate_and_deactivate_the_right_cell() {
+        let mut map = wide_map();
+        map.activate_tile(4, 1);
+        assert!(map.is_tile_active(4, 1));
+        // the cell one row down in a wrongly indexed map
+        assert!(!map.is_tile_active(1, 2));
+        assert_eq!(map.tiles_of(TileType::Active), vec![(4, 1)]);
+        map.deactivate_tile(4, 1);
+        assert!(!map.is_tile_active(4, 1));
+        assert_eq!(map.count_tiles(TileType::Active), 0);
+    }
+
+    #[test]
+    fn off_the_map_is_neither_active_nor_blocked() {
+        let mut map = wide_map();
+        for &(x, y) in [(-1, 1), (6, 1), (1, -1), (1, 3)].iter() {
+            map.activate_tile(x, y);
+            assert!(!map.is_tile_active(x, y));
+            assert!(!map.is_tile_blocked(x, y));
+        }
+        // an x past the right edge must not wrap onto the next row
+        assert!(!map.is_tile_active(0, 2));
+        assert_eq!(map.count_tiles(TileType::Active), 0);
+    }
+
+    #[test]
+    fn border_is_blocked() {
+        let map = wide_map();
+        assert!(map.is_tile_blocked(0, 0));
+        assert!(map.is_tile_blocked(5, 0));
+        assert!(map.is_tile_blocked(0, 2));
+        assert!(map.is_tile_blocked(5, 2));
+        assert!(map.is_tile_blocked(3, 0));
+        assert!(!map.is_tile_blocked(1, 1));
+        assert!(!map.is_tile_blocked(4, 1));
+    }
+
+    #[test]
+    fn slime_is_drawn_on_its_cell() {
+        let mut map = wide_map();
+        map.slime_tile(4, 2, 10);
+        let mut r = MockRenderer::new(150.0, 75.0);
+        map.draw_slime(5, &mut r);
+        match r.drawn.as_slice() {
+            [Drawn::Cell(x, y, _)] => assert_eq!((*x, *y), (4.0, 2.0)),
+            other => panic!("drew {:?}", other),
+        }
+    }
+
+    #[test]
+    fn grid_covers_the_whole_map() {
+        let map = wide_map();
+        let mut r = MockRenderer::new(150.0, 75.0);
+        map.draw_grid(&mut r);
+        // one line either side of every column and row
+        assert_eq!(r.drawn.len(), 7 + 4);
+    }
+
+    #[test]
+    fn grid_size_in_tiles() {
+        assert_eq!(grid_size(800, 600), (32, 24));
+        assert_eq!(grid_size(TILE_W as u32 * 6, TILE_H as u32 * 3), (6, 3));
+    }
+
+    #[test]
+    fn layers_merge_with_objects_on_top() {
+        let base = map_file("layer_base", "1111\n1001\n1111\n");
+        let objects = map_file("layer_objects", "....\n.2a.\n..a.\n");
+        let (map, info) = load_layered(&base, &objects).unwrap();
+        assert_eq!((map.width, map.height), (4, 3));
+        // walls the object layer leaves alone
+        assert!(map.is_tile_blocked(0, 0));
+        assert!(map.is_tile_blocked(3, 1));
+        assert!(map.is_tile_active(1, 1));
+        assert_eq!(map.portal_at(2, 1), Some(0));
+        // an object over a wall replaces it
+        assert_eq!(map.portal_at(2, 2), Some(0));
+        assert!(!map.is_tile_blocked(2, 2));
+        assert_eq!(info.portals, vec![((2, 1), (2, 2))]);
+    }
+
+    #[test]
+    fn layers_mark_a_start_and_bonus_cells() {
+        let base = map_file("layer_marked_base", "1111\n1S01\n1111\n");
+        let objects = map_file("layer_marked_objects", "....\n..B.\n....\n");
+        let (map, info) = load_layered(&base, &objects).unwrap();
+        assert_eq!(info.start, Some((1, 1)));
+        assert_eq!(info.bonus_zones, vec![(2, 1)]);
+        assert!(!map.is_tile_blocked(1, 1));
+
+        // the object layer's start wins over the base's
+        let objects = map_file("layer_start_objects", "....\n..S.\n....\n");
+        let (_, info) = load_layered(&base, &objects).unwrap();
+        assert_eq!(info.start, Some((2, 1)));
+    }
+
+    #[test]
+    fn only_layers_leave_cells_empty() {
+        let path = map_file("dot_in_map", "11\n1.\n");
+        assert!(load_from_file(&path).is_err());
+        assert!(load_layer(&path).is_ok());
+    }
+
+    #[test]
+    fn layers_of_different_sizes_are_an_error() {
+        let base = map_file("layer_small", "111\n111\n");
+        let objects = map_file("layer_big", "....\n....\n");
+        assert!(load_layered(&base, &objects).is_err());
+    }
+
+    #[test]
+    fn shuffled_obstacles_keep_their_count_and_leave_the_map_connected() {
+        use rand::SeedableRng;
+
+        let mut map = Map::new(12, 8, blank_map(12, 8));
+        for &(x, y) in [(3, 3), (4, 3), (5, 3), (8, 5), (8, 6)].iter() {
+            map.set_tile_at(x, y, TileType::Obstacle);
+        }
+        let keep_clear = [(1, 1), (2, 1), (3, 1)];
+        for seed in 0..20 {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            map.shuffle_obstacles(&mut rng, &keep_clear);
+            assert_eq!(map.count_tiles(TileType::Obstacle), 5, "seed {}", seed);
+            assert!(map.is_connected(), "seed {}", seed);
+            assert!(keep_clear.iter().all(|&(x, y)| !map.is_tile_blocked(x, y)));
+        }
+    }
+
+    #[test]
+    fn spawn_zone_is_cleared_of_walls() {
+        let tiles = vec![Tile::new(TileType::Blocked); 10 * 10];
+        let mut map = Map::new(10, 10, tiles);
+        map.set_tile_at(6, 6, TileType::Obstacle);
+        map.clear_spawn_zone((5, 5), 2);
+        for y in 3..=7 {
+            for x in 3..=7 {
+                assert!(!map.is_tile_blocked(x, y), "{}, {} still blocked", x, y);
+            }
+        }
+        // just outside the radius is left alone
+        assert!(map.is_tile_blocked(2, 5));
+        assert!(map.is_tile_blocked(5, 8));
+    }
+
+    #[test]
+    fn spawn_zone_at_the_edge_stays_on_the_map() {
+        let mut map = wide_map();
+        map.clear_spawn_zone((0, 0), 1);
+        assert!(!map.is_tile_blocked(0, 0));
+        assert!(!map.is_tile_blocked(1, 1));
+        assert_eq!(map.tiles.len(), 18);
+    }
+
+    /// corners Map::draw gave the renderer
+    fn drawn_corners(map: &mut Map, theme: &Theme) -> Vec<(f32, f32, Color)> {
+        let mut r = MockRenderer::new(800.0, 600.0);
+        map.draw(theme, &mut r);
+        match r.drawn.pop() {
+            Some(Drawn::Quads(corners)) => corners,
+            other => panic!("drew {:?}", other),
+        }
+    }
+
+    #[test]
+    fn draw_lays_tiles_out_by_the_map_width() {
+        let theme = ThemeName::Classic.theme();
+        let mut map = Map::new(10, 3, blank_map(10, 3));
+        map.activate_tile(7, 1);
+        let corners = drawn_corners(&mut map, &theme);
+        // top left corner of tile `i`, the first of its vertices
+        let top_left = |i: usize| corners[i * TILE_VERTICES];
+        assert_eq!(top_left(0), (0.0, 0.0, theme.wall_edge));
+        assert_eq!(top_left(9), (9.0 * TILE_W, 0.0, theme.wall_edge));
+        assert_eq!(top_left(10), (0.0, TILE_H, theme.wall_edge));
+        assert_eq!(top_left(12), (2.0 * TILE_W, TILE_H, theme.floor));
+        assert_eq!(top_left(17), (7.0 * TILE_W, TILE_H, theme.food));
+        assert_eq!(top_left(29), (9.0 * TILE_W, 2.0 * TILE_H, theme.wall_edge));
+    }
+
+    #[test]
+    fn generated_map_is_walled_and_keeps_the_start_open() {
+        use rand::SeedableRng;
+
+        for seed in 0..10 {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            // dense enough that the start would be walled by chance
+            let tiles = generate_map(&mut rng, 14, 9, 0.6, (1, 1));
+            assert_eq!(tiles.len(), 14 * 9);
+            let map = Map::new(14, 9, tiles);
+            for x in 0..14 {
+                assert!(map.is_tile_blocked(x, 0) && map.is_tile_blocked(x, 8));
+            }
+            for y in 0..9 {
+                assert!(map.is_tile_blocked(0, y) && map.is_tile_blocked(13, y));
+            }
+            assert!(!map.is_tile_blocked(1, 1));
+            assert!(!map.is_tile_blocked(2, 2));
+        }
+    }
+
+    #[test]
+    fn portal_exits_are_the_other_end() {
+        let path = map_file("portal_exit", "a0b\n000\nb0a\n");
+        let (tiles, w, h, _) = load_from_file(&path).unwrap();
+        let map = Map::new(w, h, tiles);
+        assert_eq!(map.portal_exit(0, 0), Some((2, 2)));
+        assert_eq!(map.portal_exit(2, 2), Some((0, 0)));
+        assert_eq!(map.portal_exit(2, 0), Some((0, 2)));
+        assert_eq!(map.portal_exit(1, 1), None);
+        assert_eq!(map.portal_exit(-1, 0), None);
+    }
+
+    #[test]
+    fn file_characters() {
+        let path = map_file("characters", "1111\n1S21\n1B31\n1PP1\n");
+        let (tiles, w, h, info) = load_from_file(&path).unwrap();
+        assert_eq!((w, h), (4, 4));
+        let types: Vec<TileType> = tiles.iter().map(|t| t.tile_type.clone()).collect();
+        assert_eq!(types[0], TileType::Blocked);
+        assert_eq!(types[5], TileType::NonBlocked);
+        assert_eq!(types[6], TileType::Active);
+        assert_eq!(types[10], TileType::Obstacle);
+        assert_eq!(types[13], TileType::Portal(26));
+        assert_eq!(info.start, Some((1, 1)));
+        assert_eq!(info.bonus_zones, vec![(1, 2)]);
+        assert_eq!(info.portals, vec![((1, 3), (2, 3))]);
+    }
+
+    #[test]
+    fn second_start_is_an_error() {
+        let path = map_file("two_starts", "S0\n0S\n");
+        assert!(load_from_file(&path).is_err());
+    }
+
+    #[test]
+    fn odd_portal_count_is_an_error() {
+        assert!(load_from_file(&map_file("one_p", "P0\n00\n")).is_err());
+        assert!(load_from_file(&map_file("one_a", "a0\n00\n")).is_err());
+    }
+
+    #[test]
+    fn good_map_with_spaces_and_blank_lines_loads() {
+        let path = map_file("good", "1 1 1\n1 0 1\t\n\n1 1 1\n\n");
+        let (tiles, w, h, _) = load_from_file(&path).unwrap();
+        assert_eq!((w, h, tiles.len()), (3, 3, 9));
+    }
+
+    #[test]
+    fn stray_character_names_its_line_and_column() {
+        let path = map_file("stray", "111\n10#\n111\n");
+        let err = load_from_file(&path).err().unwrap();
+        let parse = err.get_ref().unwrap().downcast_ref::<MapParseError>();
+        assert_eq!(
+            parse,
+            Some(&MapParseError {
+                line: 2,
+                col: 3,
+                found: '#'
+            })
+        );
+    }
+
+    #[test]
+    fn x_at_width_and_y_at_height_are_off_the_map() {
+        type Set = fn(&mut Map, i32, i32);
+        type Is = fn(&Map, i32, i32) -> bool;
+        let kinds: [(Set, Is); 5] = [
+            (Map::activate_tile, Map::is_tile_active),
+            (Map::poison_tile, Map::is_tile_poison),
+            (Map::bonus_tile, Map::is_tile_bonus),
+            (Map::shrink_tile, Map::is_tile_shrink),
+            (Map::boost_tile, Map::is_tile_boost),
+        ];
+        for &(set, is) in kinds.iter() {
+            // (6, 0) is (0, 1) if x isn't checked against the width
+            let mut map = wide_map();
+            set(&mut map, 1, 1);
+            set(&mut map, 0, 1);
+            assert!(is(&map, 1, 1));
+            assert!(!is(&map, 6, 0));
+            assert!(!is(&map, 7, 0));
+            assert!(!is(&map, 1, 3));
+            assert!(!map.is_tile_blocked(6, 0));
+            assert!(!map.is_tile_blocked(1, 3));
+        }
+        assert_eq!(wide_map().portal_at(6, 0), None);
+    }
+
+    #[test]
+    fn activating_one_tile_marks_one_index_dirty() {
+        let theme = ThemeName::Classic.theme();
+        let mut map = Map::new(10, 3, blank_map(10, 3));
+        drawn_corners(&mut map, &theme);
+        assert!(map.dirty.is_empty());
+
+        map.activate_tile(7, 1);
+        map.activate_tile(7, 1);
+        assert_eq!(map.dirty, vec![17]);
+
+        let corners = drawn_corners(&mut map, &theme);
+        assert!(map.dirty.is_empty());
+        assert_eq!(corners[17 * TILE_VERTICES].2, theme.food);
+    }
+
+    #[test]
+    fn one_tile_and_one_batch_slot_per_cell() {
+        let theme = ThemeName::Classic.theme();
+        let mut map = Map::new(10, 3, blank_map(10, 3));
+        assert_eq!(map.tiles.len(), 10 * 3);
+        let corners = drawn_corners(&mut map, &theme);
+        assert_eq!(corners.len(), 10 * 3 * TILE_VERTICES);
+    }
+
+    #[test]
+    fn draw_is_one_call_however_big_the_map() {
+        let theme = ThemeName::Classic.theme();
+        for &(w, h) in [(6, 3), (32, 24), (64, 48)].iter() {
+            let mut map = Map::new(w, h, blank_map(w, h));
+            // drawn tile by tile this was w * h calls a frame
+            for frame in 0..3 {
+                map.activate_tile(frame + 1, 1);
+                let mut r = MockRenderer::new(800.0, 600.0);
+                map.draw(&theme, &mut r);
+                assert_eq!(r.drawn.len(), 1, "{}x{} frame {}", w, h, frame);
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! hazards that patrol the board and kill the snake like a wall
+
+use crate::map::Map;
+use crate::render::Renderer;
+use crate::theme::Theme;
+use crate::{TILE_H, TILE_W};
+
+// -----------------------------------
+// OBSTACLE
+// -----------------------------------
+/// a hazard on `cell` moving `velocity` cells every tick
+pub struct Obstacle {
+    pub cell: (i32, i32),
+    pub velocity: (i32, i32),
+}
+
+impl Obstacle {
+    pub fn new(cell: (i32, i32), velocity: (i32, i32)) -> Self {
+        Self { cell, velocity }
+    }
+
+    /// step along the path, bouncing back off walls and the map edge.
+    /// stays put when boxed in both ways
+    pub fn update(&mut self, map: &Map) {
+        let open = |(x, y): (i32, i32)| map.in_bounds(x, y) && !map.is_tile_blocked(x, y);
+        let ahead = |c: (i32, i32), v: (i32, i32)| (c.0 + v.0, c.1 + v.1);
+
+        if !open(ahead(self.cell, self.velocity)) {
+            self.velocity = (-self.velocity.0, -self.velocity.1);
+        }
+        let next = ahead(self.cell, self.velocity);
+        if open(next) {
+            self.cell = next;
+        }
+    }
+
+    /// a square inset from the tile edges, so it reads apart from walls
+    pub fn draw(&self, theme: &Theme, r: &mut dyn Renderer) {
+        let (inset_x, inset_y) = (TILE_W * 0.15, TILE_H * 0.15);
+        r.draw_rect(
+            self.cell.0 as f32 * TILE_W + inset_x,
+            self.cell.1 as f32 * TILE_H + inset_y,
+            TILE_W - 2.0 * inset_x,
+            TILE_H - 2.0 * inset_y,
+            theme.hazard,
+        );
+    }
+}
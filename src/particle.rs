@@ -0,0 +1,124 @@
+//! short lived bursts of particles, only ever drawn, nothing in the game
+//! reads them
+
+use crate::render::Renderer;
+use crate::{TILE_H, TILE_W};
+use rand::Rng;
+use sfml::graphics::Color;
+
+// -----------------------------------
+// CONSTS
+// -----------------------------------
+/// ms a particle lasts, fading out as it goes
+pub const PARTICLE_LIFE_MS: f32 = 500.0;
+/// most particles alive at once, bursts past it are cut short
+pub const MAX_PARTICLES: usize = 200;
+/// particles in one burst
+const BURST_SIZE: usize = 12;
+/// fastest a particle leaves a burst, px per second
+const BURST_SPEED: f32 = 90.0;
+
+// -----------------------------------
+// PARTICLE
+// -----------------------------------
+pub struct Particle {
+    /// px position of its middle
+    pub pos: (f32, f32),
+    /// px per second
+    pub vel: (f32, f32),
+    /// ms left before it's gone
+    pub life: f32,
+}
+
+impl Particle {
+    pub fn update(&mut self, dt_ms: f32) {
+        let dt = dt_ms / 1000.0;
+        self.pos = (self.pos.0 + self.vel.0 * dt, self.pos.1 + self.vel.1 * dt);
+        self.life -= dt_ms;
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.life > 0.0
+    }
+
+    /// a small square, more see-through the less life it has left
+    pub fn draw(&self, color: Color, r: &mut dyn Renderer) {
+        let size = TILE_W.min(TILE_H) * 0.2;
+        let alpha = (self.life / PARTICLE_LIFE_MS).clamp(0.0, 1.0) * color.a as f32;
+        r.draw_rect(
+            self.pos.0 - size / 2.0,
+            self.pos.1 - size / 2.0,
+            size,
+            size,
+            Color::rgba(color.r, color.g, color.b, alpha as u8),
+        );
+    }
+}
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// particles flying out every way from the middle of `cell`. uses its own
+/// rng so a burst never changes what the game does next
+pub fn burst(particles: &mut Vec<Particle>, cell: (i32, i32)) {
+    let mut rng = rand::thread_rng();
+    let center = (
+        (cell.0 as f32 + 0.5) * TILE_W,
+        (cell.1 as f32 + 0.5) * TILE_H,
+    );
+    let room = MAX_PARTICLES.saturating_sub(particles.len());
+    for i in 0..BURST_SIZE.min(room) {
+        let angle = i as f32 / BURST_SIZE as f32 * std::f32::consts::TAU;
+        let speed = BURST_SPEED * rng.gen_range(0.4, 1.0);
+        particles.push(Particle {
+            pos: center,
+            vel: (angle.cos() * speed, angle.sin() * speed),
+            life: PARTICLE_LIFE_MS,
+        });
+    }
+}
+
+/// move every particle on by `dt_ms` and drop the ones that have run out
+pub fn update_particles(particles: &mut Vec<Particle>, dt_ms: f32) {
+    for p in particles.iter_mut() {
+        p.update(dt_ms);
+    }
+    particles.retain(Particle::is_alive);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_once_life_reaches_zero() {
+        let mut particles = vec![];
+        burst(&mut particles, (2, 3));
+        assert_eq!(particles.len(), BURST_SIZE);
+        update_particles(&mut particles, PARTICLE_LIFE_MS - 1.0);
+        assert_eq!(particles.len(), BURST_SIZE);
+        update_particles(&mut particles, 1.0);
+        assert!(particles.is_empty());
+    }
+
+    #[test]
+    fn moves_by_its_velocity() {
+        let mut p = Particle {
+            pos: (10.0, 10.0),
+            vel: (100.0, -50.0),
+            life: PARTICLE_LIFE_MS,
+        };
+        p.update(100.0);
+        assert_eq!(p.pos, (20.0, 5.0));
+        assert_eq!(p.life, PARTICLE_LIFE_MS - 100.0);
+    }
+
+    #[test]
+    fn bursts_stop_at_the_cap() {
+        let mut particles = vec![];
+        for _ in 0..MAX_PARTICLES {
+            burst(&mut particles, (1, 1));
+        }
+        assert_eq!(particles.len(), MAX_PARTICLES);
+    }
+}
@@ -0,0 +1,304 @@
+//! drawing behind a trait, so game pieces never touch a window directly
+
+use crate::{TILE_H, TILE_W};
+use rand::Rng;
+use sfml::graphics::*;
+use sfml::system::Vector2f;
+
+/// px outline round titles and menu lines, thicker than the HUD's
+const CENTERED_OUTLINE: f32 = 2.0;
+
+// -----------------------------------
+// RENDERER
+// -----------------------------------
+/// what the game pieces need to draw themselves
+pub trait Renderer {
+    fn clear(&mut self, color: Color);
+    /// fill the tile at col, row. fractions land between tiles, for pieces
+    /// sliding from one to the next
+    fn draw_cell(&mut self, col: f32, row: f32, color: Color);
+    /// fill a `w` x `h` px rectangle with its top left at px x, y
+    fn draw_rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: Color);
+    /// four corners per rectangle, see `push_rect`, all drawn in one call
+    fn draw_quads(&mut self, quads: &[Vertex]);
+    /// white `text` with a black `outline` px thick, its top left at px x, y
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, size: u32, outline: f32);
+    /// px wide `text` would be drawn at `size` with an `outline` px thick
+    fn text_width(&self, text: &str, size: u32, outline: f32) -> f32;
+    /// px size of the view being drawn to
+    fn view_size(&self) -> Vector2f;
+    fn display(&mut self);
+}
+
+/// draws to an SFML window, one shape reused for every rectangle
+pub struct SfmlRenderer<'w> {
+    window: &'w mut RenderWindow,
+    font: &'w Font,
+    rect: RectangleShape<'static>,
+}
+
+impl<'w> SfmlRenderer<'w> {
+    pub fn new(window: &'w mut RenderWindow, font: &'w Font) -> Self {
+        Self {
+            window,
+            font,
+            rect: RectangleShape::new(),
+        }
+    }
+
+    /// the window underneath, for drawing the trait doesn't cover
+    pub fn window(&mut self) -> &mut RenderWindow {
+        self.window
+    }
+}
+
+impl<'w> Renderer for SfmlRenderer<'w> {
+    fn clear(&mut self, color: Color) {
+        self.window.clear(color);
+    }
+
+    fn draw_cell(&mut self, col: f32, row: f32, color: Color) {
+        self.draw_rect(col * TILE_W, row * TILE_H, TILE_W, TILE_H, color);
+    }
+
+    fn draw_rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: Color) {
+        self.rect.set_position((x, y));
+        self.rect.set_size((w, h));
+        self.rect.set_fill_color(color);
+        self.window.draw(&self.rect);
+    }
+
+    fn draw_quads(&mut self, quads: &[Vertex]) {
+        self.window
+            .draw_primitives(quads, PrimitiveType::Quads, RenderStates::default());
+    }
+
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, size: u32, outline: f32) {
+        let mut t = Text::new(text, self.font, size);
+        t.set_fill_color(Color::WHITE);
+        t.set_outline_color(Color::BLACK);
+        t.set_outline_thickness(outline);
+        t.set_position((x, y));
+        self.window.draw(&t);
+    }
+
+    fn text_width(&self, text: &str, size: u32, outline: f32) -> f32 {
+        let mut t = Text::new(text, self.font, size);
+        t.set_outline_thickness(outline);
+        t.local_bounds().width
+    }
+
+    fn view_size(&self) -> Vector2f {
+        self.window.view().size()
+    }
+
+    fn display(&mut self) {
+        self.window.display();
+    }
+}
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// draw a line of text centered horizontally on the view at y
+pub fn draw_centered_text(text: &str, y: f32, size: u32, r: &mut dyn Renderer) {
+    let width = r.text_width(text, size, CENTERED_OUTLINE);
+    let x = (r.view_size().x - width) / 2.0;
+    r.draw_text(text, x, y, size, CENTERED_OUTLINE);
+}
+
+/// add a `w` x `h` px rectangle with its top left at px x, y to a batch for
+/// `draw_quads`, later ones land on top
+pub fn push_rect(quads: &mut Vec<Vertex>, x: f32, y: f32, w: f32, h: f32, color: Color) {
+    for &(cx, cy) in [(x, y), (x + w, y), (x + w, y + h), (x, y + h)].iter() {
+        quads.push(Vertex::with_pos_color(Vector2f::new(cx, cy), color));
+    }
+}
+
+/// view jitter `elapsed_ms` into a screen shake, up to `magnitude` px each
+/// way fading to none at `duration_ms`. its own rng, so replays still match
+pub fn shake_offset(elapsed_ms: i32, duration_ms: i32, magnitude: f32) -> Vector2f {
+    if elapsed_ms >= duration_ms || magnitude <= 0.0 {
+        return Vector2f::new(0.0, 0.0);
+    }
+    let reach = magnitude * (1.0 - elapsed_ms.max(0) as f32 / duration_ms as f32);
+    let mut rng = rand::thread_rng();
+    Vector2f::new(rng.gen_range(-reach, reach), rng.gen_range(-reach, reach))
+}
+
+/// center for a `view_size` camera on `target` that shows nothing past the
+/// edges of a `map_size` map. an axis the map doesn't fill stays put, lined
+/// up with the top left like a view that doesn't move
+pub fn clamp_camera(target: Vector2f, view_size: Vector2f, map_size: Vector2f) -> Vector2f {
+    let axis = |target: f32, view: f32, map: f32| {
+        let half = view / 2.0;
+        if map <= view {
+            half
+        } else {
+            target.clamp(half, map - half)
+        }
+    };
+    Vector2f::new(
+        axis(target.x, view_size.x, map_size.x),
+        axis(target.y, view_size.y, map_size.y),
+    )
+}
+
+/// a renderer that notes what it was asked to draw instead of drawing it
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::*;
+
+    /// one call made on a MockRenderer
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Drawn {
+        Clear(Color),
+        Cell(f32, f32, Color),
+        Rect(f32, f32, f32, f32, Color),
+        /// (x, y, color) of each corner
+        Quads(Vec<(f32, f32, Color)>),
+        Text(String, f32, f32, u32),
+        Display,
+    }
+
+    /// every char `CHAR_WIDTH` of the text size wide, so widths are easy to
+    /// work out in a test
+    pub const CHAR_WIDTH: f32 = 0.5;
+
+    pub struct MockRenderer {
+        pub view: Vector2f,
+        pub drawn: Vec<Drawn>,
+    }
+
+    impl MockRenderer {
+        pub fn new(width: f32, height: f32) -> Self {
+            Self {
+                view: Vector2f::new(width, height),
+                drawn: vec![],
+            }
+        }
+    }
+
+    impl Renderer for MockRenderer {
+        fn clear(&mut self, color: Color) {
+            self.drawn.push(Drawn::Clear(color));
+        }
+
+        fn draw_cell(&mut self, col: f32, row: f32, color: Color) {
+            self.drawn.push(Drawn::Cell(col, row, color));
+        }
+
+        fn draw_rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: Color) {
+            self.drawn.push(Drawn::Rect(x, y, w, h, color));
+        }
+
+        fn draw_quads(&mut self, quads: &[Vertex]) {
+            let corners = quads
+                .iter()
+                .map(|v| (v.position.x, v.position.y, v.color))
+                .collect();
+            self.drawn.push(Drawn::Quads(corners));
+        }
+
+        fn draw_text(&mut self, text: &str, x: f32, y: f32, size: u32, _outline: f32) {
+            self.drawn.push(Drawn::Text(text.to_string(), x, y, size));
+        }
+
+        fn text_width(&self, text: &str, size: u32, outline: f32) -> f32 {
+            text.chars().count() as f32 * size as f32 * CHAR_WIDTH + outline * 2.0
+        }
+
+        fn view_size(&self) -> Vector2f {
+            self.view
+        }
+
+        fn display(&mut self) {
+            self.drawn.push(Drawn::Display);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::{Drawn, MockRenderer};
+    use super::*;
+
+    #[test]
+    fn centered_text_is_centered_on_the_view() {
+        let mut r = MockRenderer::new(800.0, 600.0);
+        // 10 chars at 20 is 100 px, plus 2 px of outline each side
+        draw_centered_text("0123456789", 50.0, 20, &mut r);
+        let x = (800.0 - 104.0) / 2.0;
+        assert_eq!(r.drawn, vec![Drawn::Text("0123456789".into(), x, 50.0, 20)]);
+    }
+
+    #[test]
+    fn centered_text_follows_the_view_size() {
+        let mut r = MockRenderer::new(200.0, 100.0);
+        draw_centered_text("ab", 10.0, 10, &mut r);
+        match &r.drawn[0] {
+            Drawn::Text(_, x, _, _) => assert_eq!(*x, (200.0 - 14.0) / 2.0),
+            other => panic!("drew {:?}", other),
+        }
+    }
+
+    #[test]
+    fn push_rect_adds_four_corners() {
+        let mut quads = vec![];
+        push_rect(&mut quads, 1.0, 2.0, 3.0, 4.0, Color::RED);
+        let corners: Vec<(f32, f32)> = quads.iter().map(|v| (v.position.x, v.position.y)).collect();
+        assert_eq!(
+            corners,
+            vec![(1.0, 2.0), (4.0, 2.0), (4.0, 6.0), (1.0, 6.0)]
+        );
+        assert!(quads.iter().all(|v| v.color == Color::RED));
+    }
+
+    #[test]
+    fn no_shake_at_or_after_the_duration() {
+        let still = Vector2f::new(0.0, 0.0);
+        assert_eq!(shake_offset(300, 300, 6.0), still);
+        assert_eq!(shake_offset(1_000, 300, 6.0), still);
+        assert_eq!(shake_offset(0, 300, 0.0), still);
+    }
+
+    #[test]
+    fn shake_stays_within_its_fading_reach() {
+        for _ in 0..100 {
+            let v = shake_offset(150, 300, 6.0);
+            assert!(v.x.abs() <= 3.0 && v.y.abs() <= 3.0, "{:?}", v);
+        }
+    }
+
+    #[test]
+    fn camera_follows_inside_the_map() {
+        let view = Vector2f::new(200.0, 100.0);
+        let map = Vector2f::new(1000.0, 500.0);
+        let target = Vector2f::new(400.0, 300.0);
+        assert_eq!(clamp_camera(target, view, map), target);
+    }
+
+    #[test]
+    fn camera_stops_at_the_map_edges() {
+        let view = Vector2f::new(200.0, 100.0);
+        let map = Vector2f::new(1000.0, 500.0);
+        assert_eq!(
+            clamp_camera(Vector2f::new(10.0, 10.0), view, map),
+            Vector2f::new(100.0, 50.0)
+        );
+        assert_eq!(
+            clamp_camera(Vector2f::new(990.0, 490.0), view, map),
+            Vector2f::new(900.0, 450.0)
+        );
+    }
+
+    #[test]
+    fn camera_stays_put_on_an_axis_the_map_fits() {
+        let view = Vector2f::new(200.0, 100.0);
+        let map = Vector2f::new(1000.0, 80.0);
+        assert_eq!(
+            clamp_camera(Vector2f::new(500.0, 70.0), view, map),
+            Vector2f::new(500.0, 50.0)
+        );
+    }
+}
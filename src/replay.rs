@@ -0,0 +1,268 @@
+//! recording a game's moves so it can be played back
+
+use crate::snake::Direction;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result, Write};
+
+// -----------------------------------
+// REPLAY
+// -----------------------------------
+/// what a game was set up with, the map and rules it has to be played on
+/// again for the moves to come out the same
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayHeader {
+    /// name of the map, as the map select screen shows it
+    pub map: String,
+    /// seed the map's random walls were made from
+    pub map_seed: u64,
+    /// (width, height) of the map in tiles
+    pub size: (i32, i32),
+    /// names of the mode and difficulty, as `{:?}` prints them
+    pub mode: String,
+    pub difficulty: String,
+    pub start_length: usize,
+    /// did the snakes wrap round the map edges
+    pub wrap: bool,
+}
+
+/// the rng seed a game started from, what it was played on and the
+/// direction each snake moved in on every tick, enough to play the same
+/// game again
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub header: ReplayHeader,
+    pub directions: Vec<Direction>,
+    /// player two's moves, empty without one
+    pub rival_directions: Vec<Direction>,
+}
+
+fn move_char(dir: Direction) -> char {
+    match dir {
+        Direction::Up => 'U',
+        Direction::Down => 'D',
+        Direction::Left => 'L',
+        Direction::Right => 'R',
+    }
+}
+
+fn invalid(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// a line of U/D/L/R as directions
+fn parse_moves(text: &str) -> Result<Vec<Direction>> {
+    text.chars()
+        .map(|c| match c {
+            'U' => Ok(Direction::Up),
+            'D' => Ok(Direction::Down),
+            'L' => Ok(Direction::Left),
+            'R' => Ok(Direction::Right),
+            _ => Err(invalid(format!("bad move {:?}", c))),
+        })
+        .collect()
+}
+
+impl Replay {
+    pub fn new(seed: u64, header: ReplayHeader) -> Self {
+        Self {
+            seed,
+            header,
+            directions: vec![],
+            rival_directions: vec![],
+        }
+    }
+
+    /// note the direction moved in this tick
+    pub fn record(&mut self, dir: Direction) {
+        self.directions.push(dir);
+    }
+
+    /// note the direction player two moved in this tick
+    pub fn record_rival(&mut self, dir: Direction) {
+        self.rival_directions.push(dir);
+    }
+
+    /// direction moved in on tick `tick`, None past the end of the recording
+    pub fn direction_at(&self, tick: usize) -> Option<Direction> {
+        self.directions.get(tick).copied()
+    }
+
+    /// direction player two moved in on tick `tick`
+    pub fn rival_direction_at(&self, tick: usize) -> Option<Direction> {
+        self.rival_directions.get(tick).copied()
+    }
+
+    /// a `key value` line for the seed and each part of the header, then
+    /// one U/D/L/R per tick on a `moves` line and a `rival` line
+    pub fn to_text(&self) -> String {
+        let h = &self.header;
+        let moves: String = self.directions.iter().cloned().map(move_char).collect();
+        let rival: String = self
+            .rival_directions
+            .iter()
+            .cloned()
+            .map(move_char)
+            .collect();
+        format!(
+            "seed {}\nmap {}\nmap_seed {}\nsize {} {}\nmode {}\ndifficulty {}\n\
+start_length {}\nwrap {}\nmoves {}\nrival {}\n",
+            self.seed,
+            h.map,
+            h.map_seed,
+            h.size.0,
+            h.size.1,
+            h.mode,
+            h.difficulty,
+            h.start_length,
+            h.wrap,
+            moves,
+            rival
+        )
+    }
+
+    /// a replay written by to_text, an error naming the first line missing
+    /// or corrupt
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut seed = None;
+        let mut map = None;
+        let mut map_seed = None;
+        let mut size = None;
+        let mut mode = None;
+        let mut difficulty = None;
+        let mut start_length = None;
+        let mut wrap = None;
+        let mut directions = None;
+        let mut rival_directions = vec![];
+        for line in text.lines() {
+            let mut parts = line.splitn(2, ' ');
+            let key = parts.next().unwrap_or("");
+            let value = parts.next().unwrap_or("").trim();
+            let bad = || invalid(format!("bad {} {:?}", key, value));
+            match key {
+                "seed" => seed = Some(value.parse().map_err(|_| bad())?),
+                "map" if !value.is_empty() => map = Some(value.to_string()),
+                "map_seed" => map_seed = Some(value.parse().map_err(|_| bad())?),
+                "size" => {
+                    let mut wh = value.split_whitespace().map(str::parse::<i32>);
+                    size = match (wh.next(), wh.next()) {
+                        (Some(Ok(w)), Some(Ok(h))) => Some((w, h)),
+                        _ => return Err(bad()),
+                    };
+                }
+                "mode" if !value.is_empty() => mode = Some(value.to_string()),
+                "difficulty" if !value.is_empty() => difficulty = Some(value.to_string()),
+                "start_length" => start_length = Some(value.parse().map_err(|_| bad())?),
+                "wrap" => wrap = Some(value.parse().map_err(|_| bad())?),
+                "moves" => directions = Some(parse_moves(value)?),
+                "rival" => rival_directions = parse_moves(value)?,
+                _ => {}
+            }
+        }
+
+        let missing = |key: &str| invalid(format!("no {}", key));
+        Ok(Self {
+            seed: seed.ok_or_else(|| missing("seed"))?,
+            header: ReplayHeader {
+                map: map.ok_or_else(|| missing("map"))?,
+                map_seed: map_seed.ok_or_else(|| missing("map_seed"))?,
+                size: size.ok_or_else(|| missing("size"))?,
+                mode: mode.ok_or_else(|| missing("mode"))?,
+                difficulty: difficulty.ok_or_else(|| missing("difficulty"))?,
+                start_length: start_length.ok_or_else(|| missing("start_length"))?,
+                wrap: wrap.ok_or_else(|| missing("wrap"))?,
+            },
+            directions: directions.ok_or_else(|| missing("moves"))?,
+            rival_directions,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let mut file = File::create(path)?;
+        write!(file, "{}", self.to_text())?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|e| invalid(format!("{}: {}", path, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay() -> Replay {
+        let header = ReplayHeader {
+            map: "big room".to_string(),
+            map_seed: 99,
+            size: (32, 24),
+            mode: "Versus".to_string(),
+            difficulty: "Hard".to_string(),
+            start_length: 4,
+            wrap: true,
+        };
+        let mut replay = Replay::new(1234, header);
+        for &dir in [Direction::Up, Direction::Left, Direction::Down].iter() {
+            replay.record(dir);
+            replay.record_rival(Direction::Right);
+        }
+        replay
+    }
+
+    #[test]
+    fn round_trips() {
+        let replay = replay();
+        assert_eq!(Replay::parse(&replay.to_text()).unwrap(), replay);
+    }
+
+    #[test]
+    fn directions_by_tick() {
+        let replay = replay();
+        assert_eq!(replay.direction_at(1), Some(Direction::Left));
+        assert_eq!(replay.rival_direction_at(2), Some(Direction::Right));
+        assert_eq!(replay.direction_at(3), None);
+    }
+
+    #[test]
+    fn no_rival_line_means_no_rival_moves() {
+        let text: String = replay()
+            .to_text()
+            .lines()
+            .filter(|l| !l.starts_with("rival"))
+            .map(|l| format!("{}\n", l))
+            .collect();
+        assert!(Replay::parse(&text).unwrap().rival_directions.is_empty());
+    }
+
+    #[test]
+    fn missing_header_lines_are_errors() {
+        let text = replay().to_text();
+        let keys = [
+            "seed ",
+            "map ",
+            "map_seed",
+            "size",
+            "mode",
+            "difficulty",
+            "start_length",
+            "wrap",
+            "moves",
+        ];
+        for key in keys.iter() {
+            let without: String = text
+                .lines()
+                .filter(|l| !l.starts_with(key))
+                .map(|l| format!("{}\n", l))
+                .collect();
+            assert!(Replay::parse(&without).is_err(), "no {} line", key);
+        }
+    }
+
+    #[test]
+    fn bad_moves_are_errors() {
+        let text = replay().to_text().replace("moves ULD", "moves UXD");
+        assert!(Replay::parse(&text).is_err());
+    }
+}
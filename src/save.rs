@@ -0,0 +1,264 @@
+//! a game in progress saved on F5 and loaded back on F9
+
+use crate::map::Map;
+use crate::snake::Direction;
+use std::fs::File;
+use std::io::{ErrorKind, Result, Write};
+
+// -----------------------------------
+// SAVED GAME
+// -----------------------------------
+/// a game in progress as written by save_game
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavedGame {
+    /// name of the map played on, as the map select screen shows it
+    pub map: String,
+    /// name of the mode played, as `{:?}` prints it
+    pub mode: String,
+    /// head first, then the tail in order
+    pub cells: Vec<(i32, i32)>,
+    pub dir: Direction,
+    pub score: u32,
+    pub food: Vec<(i32, i32)>,
+}
+
+impl SavedGame {
+    /// can the game go back on `map`, every cell on the map and none a wall
+    pub fn fits(&self, map: &Map) -> bool {
+        self.cells
+            .iter()
+            .chain(self.food.iter())
+            .all(|&(x, y)| map.in_bounds(x, y) && !map.is_tile_blocked(x, y))
+    }
+}
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// `x,y x,y ...` as a list of cells
+fn parse_cells(text: &str) -> Option<Vec<(i32, i32)>> {
+    text.split_whitespace()
+        .map(|pair| {
+            let mut parts = pair.splitn(2, ',');
+            let x = parts.next()?.parse().ok()?;
+            let y = parts.next()?.parse().ok()?;
+            Some((x, y))
+        })
+        .collect()
+}
+
+fn format_cells(cells: &[(i32, i32)]) -> String {
+    cells
+        .iter()
+        .map(|(x, y)| format!("{},{}", x, y))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn direction_from_name(name: &str) -> Option<Direction> {
+    match name {
+        "Up" => Some(Direction::Up),
+        "Down" => Some(Direction::Down),
+        "Left" => Some(Direction::Left),
+        "Right" => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+/// the map, mode, score, facing, snake (head first) and food as one
+/// `key value` line each
+pub fn format_save(game: &SavedGame) -> String {
+    format!(
+        "map {}\nmode {}\nscore {}\ndir {:?}\nsnake {}\nfood {}\n",
+        game.map,
+        game.mode,
+        game.score,
+        game.dir,
+        format_cells(&game.cells),
+        format_cells(&game.food)
+    )
+}
+
+/// a game written by format_save, None if a line is missing or corrupt
+pub fn parse_save(text: &str) -> Option<SavedGame> {
+    let mut map = None;
+    let mut mode = None;
+    let mut score = None;
+    let mut dir = None;
+    let mut cells = None;
+    let mut food = None;
+    for line in text.lines() {
+        let mut parts = line.splitn(2, ' ');
+        let key = parts.next().unwrap_or("");
+        let value = parts.next().unwrap_or("").trim();
+        match key {
+            "map" => map = Some(value.to_string()).filter(|v| !v.is_empty()),
+            "mode" => mode = Some(value.to_string()).filter(|v| !v.is_empty()),
+            "score" => score = value.parse().ok(),
+            "dir" => dir = direction_from_name(value),
+            "snake" => cells = parse_cells(value),
+            "food" => food = parse_cells(value),
+            _ => {}
+        }
+    }
+
+    let cells = cells.filter(|c| !c.is_empty())?;
+    Some(SavedGame {
+        map: map?,
+        mode: mode?,
+        cells,
+        dir: dir?,
+        score: score?,
+        food: food?,
+    })
+}
+
+/// the hardcore save kept beside the `path` one, a file of its own so
+/// wiping it on death leaves an ordinary save alone
+pub fn hardcore_save_path(path: &str) -> String {
+    match path.strip_suffix(".txt") {
+        Some(stem) => format!("{}_hardcore.txt", stem),
+        None => format!("{}_hardcore", path),
+    }
+}
+
+pub fn save_game(path: &str, game: &SavedGame) -> Result<()> {
+    let mut file = File::create(path)?;
+    write!(file, "{}", format_save(game))?;
+    Ok(())
+}
+
+/// game saved by save_game, None if the file is missing or corrupt
+pub fn load_game(path: &str) -> Option<SavedGame> {
+    let text = std::fs::read_to_string(path).ok()?;
+    parse_save(&text)
+}
+
+/// delete a save, one that isn't there is already gone
+pub fn wipe_save(path: &str) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{blank_map, Map};
+
+    fn saved() -> SavedGame {
+        SavedGame {
+            map: "data".to_string(),
+            mode: "Timed".to_string(),
+            cells: vec![(5, 4), (4, 4), (3, 4)],
+            dir: Direction::Right,
+            score: 12,
+            food: vec![(9, 2)],
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let game = saved();
+        assert_eq!(parse_save(&format_save(&game)), Some(game));
+    }
+
+    #[test]
+    fn map_names_keep_their_spaces() {
+        let mut game = saved();
+        game.map = "big room".to_string();
+        assert_eq!(parse_save(&format_save(&game)).unwrap().map, "big room");
+    }
+
+    #[test]
+    fn missing_lines_are_rejected() {
+        let text = format_save(&saved());
+        for key in ["map", "mode", "score", "dir", "snake", "food"].iter() {
+            let without: String = text
+                .lines()
+                .filter(|l| !l.starts_with(key))
+                .map(|l| format!("{}\n", l))
+                .collect();
+            assert_eq!(parse_save(&without), None, "no {} line", key);
+        }
+    }
+
+    #[test]
+    fn corrupt_values_are_rejected() {
+        let text = format_save(&saved());
+        assert_eq!(parse_save(&text.replace("dir Right", "dir Sideways")), None);
+        assert_eq!(parse_save(&text.replace("score 12", "score lots")), None);
+        assert_eq!(parse_save(&text.replace("5,4", "5;4")), None);
+        assert_eq!(
+            parse_save(&text.replace("snake 5,4 4,4 3,4", "snake")),
+            None
+        );
+    }
+
+    #[test]
+    fn fits_open_cells() {
+        // walls round the edge of a 12x6 map
+        let map = Map::new(12, 6, blank_map(12, 6));
+        assert!(saved().fits(&map));
+    }
+
+    #[test]
+    fn off_the_map_does_not_fit() {
+        let map = Map::new(12, 6, blank_map(12, 6));
+        let mut game = saved();
+        game.cells.push((3, 6));
+        assert!(!game.fits(&map));
+        let mut game = saved();
+        game.food = vec![(-1, 2)];
+        assert!(!game.fits(&map));
+    }
+
+    #[test]
+    fn walls_do_not_fit() {
+        let map = Map::new(12, 6, blank_map(12, 6));
+        let mut game = saved();
+        game.cells = vec![(1, 1), (0, 1)];
+        assert!(!game.fits(&map));
+        let mut game = saved();
+        game.food = vec![(11, 3)];
+        assert!(!game.fits(&map));
+    }
+
+    #[test]
+    fn no_food_is_allowed() {
+        let mut game = saved();
+        game.food.clear();
+        assert_eq!(parse_save(&format_save(&game)), Some(game));
+    }
+
+    #[test]
+    fn hardcore_saves_sit_beside_the_ordinary_one() {
+        assert_eq!(
+            hardcore_save_path("assets/savegame.txt"),
+            "assets/savegame_hardcore.txt"
+        );
+        assert_eq!(hardcore_save_path("save"), "save_hardcore");
+    }
+
+    #[test]
+    fn classic_save_survives_a_hardcore_death() {
+        let dir = std::env::temp_dir();
+        let path = dir.join("rust_snake_test_save_classic.txt");
+        let path = path.to_str().unwrap();
+        let hardcore = hardcore_save_path(path);
+        let mut game = saved();
+        game.mode = "Classic".to_string();
+        save_game(path, &game).unwrap();
+        let mut run = saved();
+        run.mode = "Hardcore".to_string();
+        save_game(&hardcore, &run).unwrap();
+
+        // what a hardcore death does
+        wipe_save(&hardcore).unwrap();
+        assert_eq!(load_game(&hardcore), None);
+        assert_eq!(load_game(path), Some(game));
+        // and again with nothing left to wipe
+        assert!(wipe_save(&hardcore).is_ok());
+    }
+}
@@ -0,0 +1,376 @@
+//! one game tick with no window or sound, the game loop moves the player
+//! through `step` and reacts to what it reports
+
+use crate::map::Map;
+use crate::snake::{Direction, Snake};
+
+// -----------------------------------
+// STEP
+// -----------------------------------
+/// what happened to the snake in a step
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct StepOutcome {
+    /// moved onto food, it's gone from the map and the food list and the
+    /// snake grows on its next step
+    pub ate: bool,
+    /// ran into a wall (if walls are lethal) or its own tail, or off the
+    /// edge of a map that doesn't wrap
+    pub died: bool,
+    /// cell the snake moved off at the tail end
+    pub vacated: (i32, i32),
+}
+
+/// what eating poison does to a snake
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PoisonPenalty {
+    /// end the run, same as hitting a wall
+    EndRun,
+    /// remove this many tail segments
+    Shrink(usize),
+}
+
+impl PoisonPenalty {
+    /// punish `snake` for eating poison, true if that ends its run
+    pub fn apply(self, snake: &mut Snake) -> bool {
+        match self {
+            PoisonPenalty::EndRun => true,
+            PoisonPenalty::Shrink(n) => {
+                snake.shrink(n);
+                false
+            }
+        }
+    }
+}
+
+/// the parts of a game a step works on
+pub struct GameState<'s> {
+    pub snake: &'s mut Snake,
+    pub map: &'s mut Map,
+    pub food: &'s mut Vec<(i32, i32)>,
+    /// (width, height) to wrap round at the edges, None to leave the map
+    pub wrap: Option<(i32, i32)>,
+    pub walls_lethal: bool,
+}
+
+/// move the snake a tile, turning to `dir` first if given, then take any
+/// portal and eat any food it lands on
+pub fn step(state: &mut GameState, dir: Option<Direction>) -> StepOutcome {
+    if let Some(dir) = dir {
+        state.snake.head.set_direction(dir);
+    }
+    let vacated = state.snake.update(state.wrap);
+    take_portal(state.snake, state.map);
+
+    let (hx, hy) = state.snake.head_tile();
+    let hit_wall = state.walls_lethal && state.map.is_tile_blocked(hx, hy);
+    // with wrap on the head is always back in bounds by now
+    let off_map = !state.map.in_bounds(hx, hy);
+    let mut outcome = StepOutcome {
+        ate: false,
+        died: hit_wall || off_map || state.snake.self_collides(),
+        vacated,
+    };
+
+    if state.map.is_tile_active(hx, hy) {
+        state.map.deactivate_tile(hx, hy);
+        state.food.retain(|&f| f != (hx, hy));
+        state.snake.grow();
+        outcome.ate = true;
+    }
+    outcome
+}
+
+/// slime the `vacated` cell until `lasts` ticks after `tick`, true if the
+/// head has moved onto slime still there, which slows the snake down
+pub fn trail_slime(
+    map: &mut Map,
+    snake: &Snake,
+    vacated: (i32, i32),
+    tick: u64,
+    lasts: u64,
+) -> bool {
+    map.slime_tile(vacated.0, vacated.1, tick + lasts);
+    let (hx, hy) = snake.head_tile();
+    map.is_tile_slimed(hx, hy, tick)
+}
+
+/// direction to turn into if moving `dir` from x, y would hit a wall and
+/// exactly one perpendicular direction is open
+pub fn auto_turn(map: &Map, x: i32, y: i32, dir: Direction) -> Option<Direction> {
+    let (dx, dy) = dir.offset();
+    if !map.is_tile_blocked(x + dx, y + dy) {
+        return None;
+    }
+
+    let open: Vec<Direction> = dir
+        .perpendicular()
+        .iter()
+        .copied()
+        .filter(|d| {
+            let (dx, dy) = d.offset();
+            !map.is_tile_blocked(x + dx, y + dy)
+        })
+        .collect();
+
+    // both open is ambiguous, leave it to the player
+    if open.len() == 1 {
+        return Some(open[0]);
+    }
+    None
+}
+
+/// true once a bonus tile shown for `elapsed` ticks has outlived `lifetime`
+pub fn bonus_expired(elapsed: usize, lifetime: usize) -> bool {
+    elapsed >= lifetime
+}
+
+/// points for eating something worth `base` with the combo at `multiplier`
+pub fn score_for_eat(base: u32, multiplier: u32) -> u32 {
+    base.saturating_mul(multiplier.max(1))
+}
+
+/// take a life, returns true if that was the last one and the game is over
+pub fn lose_life(lives: &mut u32) -> bool {
+    *lives = lives.saturating_sub(1);
+    *lives == 0
+}
+
+/// a head that moved onto a portal comes out of its partner facing the same
+/// way. if the tail is on the exit the head still lands there and the snake
+/// dies running into itself
+pub fn take_portal(snake: &mut Snake, map: &Map) {
+    let (hx, hy) = snake.head_tile();
+    if let Some((ex, ey)) = map.portal_exit(hx, hy) {
+        snake.head.set_cell(ex, ey);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{blank_map, Tile, TileType};
+
+    /// 10x10 with walls round the edge
+    fn open_map() -> Map {
+        Map::new(10, 10, blank_map(10, 10))
+    }
+
+    fn step_with(
+        snake: &mut Snake,
+        map: &mut Map,
+        food: &mut Vec<(i32, i32)>,
+        wrap: Option<(i32, i32)>,
+        dir: Option<Direction>,
+    ) -> StepOutcome {
+        let mut state = GameState {
+            snake,
+            map,
+            food,
+            wrap,
+            walls_lethal: true,
+        };
+        step(&mut state, dir)
+    }
+
+    #[test]
+    fn moves_a_tile() {
+        let (mut map, mut food) = (open_map(), vec![]);
+        let mut snake = Snake::with_length((4, 4), Direction::Right, 3);
+        let outcome = step_with(&mut snake, &mut map, &mut food, None, None);
+        assert_eq!(snake.head_tile(), (5, 4));
+        assert_eq!(outcome.vacated, (2, 4));
+        assert!(!outcome.ate && !outcome.died);
+    }
+
+    #[test]
+    fn eats_food() {
+        let (mut map, mut food) = (open_map(), vec![(5, 4), (2, 2)]);
+        map.activate_tile(5, 4);
+        let mut snake = Snake::with_length((4, 4), Direction::Right, 3);
+        let outcome = step_with(&mut snake, &mut map, &mut food, None, None);
+        assert!(outcome.ate);
+        assert!(!outcome.died);
+        assert!(!map.is_tile_active(5, 4));
+        assert_eq!(food, vec![(2, 2)]);
+        // grows on the next step, the tail end stays put
+        step_with(&mut snake, &mut map, &mut food, None, None);
+        assert_eq!(snake.tail.len(), 3);
+    }
+
+    #[test]
+    fn poison_shrinks_or_ends_the_run() {
+        let mut snake = Snake::with_length((5, 5), Direction::Right, 6);
+        assert!(!PoisonPenalty::Shrink(2).apply(&mut snake));
+        assert_eq!(snake.tail.len(), 3);
+        // never past the head
+        assert!(!PoisonPenalty::Shrink(10).apply(&mut snake));
+        assert!(snake.tail.is_empty());
+        assert!(PoisonPenalty::EndRun.apply(&mut snake));
+    }
+
+    #[test]
+    fn food_still_scores_next_to_poison() {
+        let (mut map, mut food) = (open_map(), vec![(5, 4)]);
+        map.activate_tile(5, 4);
+        map.poison_tile(6, 4);
+        let mut snake = Snake::with_length((4, 4), Direction::Right, 3);
+        let outcome = step_with(&mut snake, &mut map, &mut food, None, None);
+        assert!(outcome.ate);
+        assert!(map.is_tile_poison(6, 4));
+        let outcome = step_with(&mut snake, &mut map, &mut food, None, None);
+        assert!(!outcome.ate);
+        assert!(map.is_tile_poison(snake.head_tile().0, snake.head_tile().1));
+    }
+
+    #[test]
+    fn dies_on_a_wall() {
+        let (mut map, mut food) = (open_map(), vec![]);
+        let mut snake = Snake::with_length((8, 4), Direction::Right, 3);
+        let outcome = step_with(&mut snake, &mut map, &mut food, None, None);
+        assert_eq!(snake.head_tile(), (9, 4));
+        assert!(outcome.died);
+    }
+
+    #[test]
+    fn walls_only_kill_when_lethal() {
+        let (mut map, mut food) = (open_map(), vec![]);
+        let mut snake = Snake::with_length((8, 4), Direction::Right, 3);
+        let mut state = GameState {
+            snake: &mut snake,
+            map: &mut map,
+            food: &mut food,
+            wrap: None,
+            walls_lethal: false,
+        };
+        assert!(!step(&mut state, None).died);
+    }
+
+    #[test]
+    fn dies_running_into_itself() {
+        let (mut map, mut food) = (open_map(), vec![]);
+        let mut snake = Snake::with_length((5, 5), Direction::Right, 5);
+        for &dir in [Direction::Down, Direction::Left].iter() {
+            let outcome = step_with(&mut snake, &mut map, &mut food, None, Some(dir));
+            assert!(!outcome.died);
+        }
+        let outcome = step_with(&mut snake, &mut map, &mut food, None, Some(Direction::Up));
+        assert_eq!(snake.head_tile(), (4, 5));
+        assert!(outcome.died);
+    }
+
+    #[test]
+    fn leaving_an_open_map_dies_unless_it_wraps() {
+        // no walls, the edge is open
+        let tiles = vec![Tile::new(TileType::NonBlocked); 25];
+        let mut map = Map::new(5, 5, tiles);
+        let mut food = vec![];
+        // (start, heading, where wrapping lands)
+        let edges = [
+            ((4, 2), Direction::Right, (0, 2)),
+            ((0, 2), Direction::Left, (4, 2)),
+            ((2, 0), Direction::Up, (2, 4)),
+            ((2, 4), Direction::Down, (2, 0)),
+        ];
+        for &(start, dir, wrapped) in edges.iter() {
+            let mut snake = Snake::with_length(start, dir, 2);
+            let outcome = step_with(&mut snake, &mut map, &mut food, None, None);
+            assert!(outcome.died, "left the map going {:?}", dir);
+
+            let mut snake = Snake::with_length(start, dir, 2);
+            let outcome = step_with(&mut snake, &mut map, &mut food, Some((5, 5)), None);
+            assert_eq!(snake.head_tile(), wrapped);
+            assert!(!outcome.died);
+        }
+    }
+
+    #[test]
+    fn crossing_slime_slows_until_it_dries() {
+        let (mut map, mut food) = (open_map(), vec![]);
+        let mut snake = Snake::with_length((3, 3), Direction::Right, 1);
+        // round a 2x2 loop, back onto the first cell on tick 4
+        let turns = [None, Some(Direction::Down), Some(Direction::Left)];
+        for (tick, &dir) in turns.iter().enumerate() {
+            let vacated = step_with(&mut snake, &mut map, &mut food, None, dir).vacated;
+            assert!(!trail_slime(&mut map, &snake, vacated, tick as u64, 5));
+        }
+        let vacated = step_with(&mut snake, &mut map, &mut food, None, Some(Direction::Up)).vacated;
+        assert_eq!(snake.head_tile(), (3, 3));
+        assert!(trail_slime(&mut map, &snake, vacated, 3, 5));
+        // slimed on tick 0 for 5 ticks
+        assert!(map.is_tile_slimed(3, 3, 4));
+        assert!(!map.is_tile_slimed(3, 3, 5));
+    }
+
+    #[test]
+    fn auto_turn_takes_the_only_way_open() {
+        let mut map = open_map();
+        // heading right at the east wall with a wall below too
+        map.block_tile(8, 5);
+        assert_eq!(auto_turn(&map, 8, 4, Direction::Right), Some(Direction::Up));
+    }
+
+    #[test]
+    fn auto_turn_leaves_a_choice_to_the_player() {
+        let map = open_map();
+        // up and down are both open
+        assert_eq!(auto_turn(&map, 8, 4, Direction::Right), None);
+    }
+
+    #[test]
+    fn auto_turn_out_of_a_corner() {
+        let map = open_map();
+        assert_eq!(auto_turn(&map, 1, 1, Direction::Up), Some(Direction::Right));
+        // boxed in, there is nothing to turn to
+        let mut boxed = open_map();
+        boxed.block_tile(2, 1);
+        assert_eq!(auto_turn(&boxed, 1, 1, Direction::Up), None);
+    }
+
+    #[test]
+    fn auto_turn_only_at_walls() {
+        let map = open_map();
+        assert_eq!(auto_turn(&map, 4, 4, Direction::Right), None);
+    }
+
+    #[test]
+    fn bonus_lasts_its_lifetime() {
+        assert!(!bonus_expired(0, 50));
+        assert!(!bonus_expired(49, 50));
+        assert!(bonus_expired(50, 50));
+        assert!(bonus_expired(80, 50));
+    }
+
+    #[test]
+    fn last_life_ends_the_game() {
+        let mut lives = 3;
+        assert!(!lose_life(&mut lives));
+        assert!(!lose_life(&mut lives));
+        assert_eq!(lives, 1);
+        assert!(lose_life(&mut lives));
+        assert_eq!(lives, 0);
+        // already out stays out
+        assert!(lose_life(&mut lives));
+        assert_eq!(lives, 0);
+    }
+
+    #[test]
+    fn portals_move_the_head_to_the_other_end() {
+        let mut tiles = blank_map(10, 10);
+        tiles[4 * 10 + 5] = Tile::new(TileType::Portal(0));
+        tiles[7 * 10 + 2] = Tile::new(TileType::Portal(0));
+        let (mut map, mut food) = (Map::new(10, 10, tiles), vec![]);
+        let mut snake = Snake::with_length((4, 4), Direction::Right, 2);
+        let outcome = step_with(&mut snake, &mut map, &mut food, None, None);
+        assert_eq!(snake.head_tile(), (2, 7));
+        assert!(!outcome.died);
+    }
+
+    #[test]
+    fn score_for_eat_multiplies() {
+        assert_eq!(score_for_eat(10, 1), 10);
+        assert_eq!(score_for_eat(10, 3), 30);
+        // no combo still scores
+        assert_eq!(score_for_eat(10, 0), 10);
+        assert_eq!(score_for_eat(u32::MAX, 2), u32::MAX);
+    }
+}
@@ -0,0 +1,643 @@
+//! snake pieces, the head, its tail segments and the snake that owns them
+
+use crate::input::{Action, KeyState};
+use crate::render::Renderer;
+use crate::theme::{lerp_color, Theme};
+use crate::{EYE_SCALE, INPUT_QUEUE_LEN, SEGMENT_SCALE, TILE_H, TILE_W};
+use sfml::graphics::Color;
+use std::collections::VecDeque;
+
+// -----------------------------------
+// ENUMS
+// -----------------------------------
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// tile step for one move in this direction
+    pub fn offset(&self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+
+    /// the direction pointing straight back
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    /// the two directions at right angles to this one
+    pub fn perpendicular(&self) -> [Direction; 2] {
+        match self {
+            Direction::Up | Direction::Down => [Direction::Left, Direction::Right],
+            Direction::Left | Direction::Right => [Direction::Up, Direction::Down],
+        }
+    }
+}
+
+// -----------------------------------
+// HEAD
+// -----------------------------------
+pub struct Head {
+    /// (col, row) grid cell, converted to pixels only when drawn
+    cell: (i32, i32),
+    /// cell before the last move, drawing slides from here to `cell`
+    prev_cell: (i32, i32),
+    /// px (w, h) of a tile
+    size: (f32, f32),
+    is_active: bool,
+    pub dir: Direction,
+    /// direction changes waiting for the next move
+    queue: VecDeque<Direction>,
+}
+
+impl Head {
+    pub fn from_cell(col: i32, row: i32, size: (f32, f32), dir: Direction) -> Self {
+        Self {
+            cell: (col, row),
+            prev_cell: (col, row),
+            size,
+            is_active: true,
+            dir,
+            queue: VecDeque::with_capacity(INPUT_QUEUE_LEN),
+        }
+    }
+
+    pub fn reset(&mut self, col: i32, row: i32) {
+        self.set_cell(col, row);
+        self.set_direction(Direction::Right);
+    }
+
+    /// jump straight to a cell, with nothing to slide from
+    pub fn set_cell(&mut self, col: i32, row: i32) {
+        self.cell = (col, row);
+        self.prev_cell = (col, row);
+    }
+
+    pub fn set_direction(&mut self, new_dir: Direction) {
+        self.dir = new_dir;
+        self.queue.clear();
+    }
+
+    /// buffer a direction change for a coming move, returns false if it was
+    /// dropped as a repeat, a reversal or because the queue is full
+    pub fn queue_direction(&mut self, new_dir: Direction) -> bool {
+        let last = *self.queue.back().unwrap_or(&self.dir);
+        if new_dir == last || !can_turn(last, new_dir) {
+            return false;
+        }
+        if self.queue.len() >= INPUT_QUEUE_LEN {
+            return false;
+        }
+
+        self.queue.push_back(new_dir);
+        true
+    }
+
+    /// get (col, row) grid cell
+    pub fn cell(&self) -> (i32, i32) {
+        self.cell
+    }
+
+    /// px top left `progress` (0..1) of the way from the previous cell to
+    /// this one
+    pub fn screen_pos(&self, progress: f32) -> (f32, f32) {
+        lerp_cell(self.prev_cell, self.cell, progress, self.size)
+    }
+
+    /// draw `progress` (0..1) of the way from the previous cell to this one
+    pub fn draw(&self, theme: &Theme, progress: f32, r: &mut dyn Renderer) {
+        if !self.is_active {
+            return;
+        }
+
+        let (x, y) = self.screen_pos(progress);
+        let (gap_x, w) = fit_segment(self.size.0, SEGMENT_SCALE);
+        let (gap_y, h) = fit_segment(self.size.1, SEGMENT_SCALE);
+        r.draw_rect(x + gap_x, y + gap_y, w, h, theme.head);
+
+        // one eye drawn twice toward `dir`
+        let (eye_w, eye_h) = (self.size.0 * EYE_SCALE, self.size.1 * EYE_SCALE);
+        for &(ex, ey) in eye_offsets(self.dir, self.size).iter() {
+            r.draw_rect(x + ex, y + ey, eye_w, eye_h, Color::BLACK);
+        }
+    }
+
+    pub fn inputs(&mut self, keys: &KeyState) {
+        if !self.is_active {
+            return;
+        }
+
+        if keys.is_down(Action::Up) && self.queue_direction(Direction::Up) {
+            return;
+        }
+
+        if keys.is_down(Action::Down) && self.queue_direction(Direction::Down) {
+            return;
+        }
+
+        if keys.is_down(Action::Left) && self.queue_direction(Direction::Left) {
+            return;
+        }
+
+        if keys.is_down(Action::Right) {
+            self.queue_direction(Direction::Right);
+        }
+    }
+
+    /// move one tile, wrapping around a map of `wrap` (cols, rows) if given
+    pub fn update(&mut self, wrap: Option<(i32, i32)>) {
+        if !self.is_active {
+            return;
+        }
+
+        // take the next queued turn that doesn't double back on itself
+        while let Some(next) = self.queue.pop_front() {
+            if can_turn(self.dir, next) {
+                self.dir = next;
+                break;
+            }
+        }
+
+        let (dx, dy) = self.dir.offset();
+        let mut x = self.cell.0 + dx;
+        let mut y = self.cell.1 + dy;
+
+        if let Some((w, h)) = wrap {
+            x = wrap_coord(x, w);
+            y = wrap_coord(y, h);
+        }
+
+        self.prev_cell = self.cell;
+        self.cell = (x, y);
+    }
+}
+
+// -----------------------------------
+// TAIL
+// -----------------------------------
+pub struct Tail {
+    /// (col, row) grid cell, converted to pixels only when drawn
+    cell: (i32, i32),
+    /// cell before the last move, drawing slides from here to `cell`
+    prev_cell: (i32, i32),
+    /// px (w, h) of a tile
+    size: (f32, f32),
+    is_active: bool,
+}
+
+impl Tail {
+    pub fn from_cell(col: i32, row: i32, size: (f32, f32)) -> Self {
+        Self {
+            cell: (col, row),
+            prev_cell: (col, row),
+            size,
+            is_active: true,
+        }
+    }
+
+    /// get (col, row) grid cell
+    pub fn cell(&self) -> (i32, i32) {
+        self.cell
+    }
+
+    /// draw `progress` (0..1) of the way from the previous cell to this one
+    pub fn draw(&self, color: Color, progress: f32, r: &mut dyn Renderer) {
+        if !self.is_active {
+            return;
+        }
+
+        let (x, y) = lerp_cell(self.prev_cell, self.cell, progress, self.size);
+        let (gap_x, w) = fit_segment(self.size.0, SEGMENT_SCALE);
+        let (gap_y, h) = fit_segment(self.size.1, SEGMENT_SCALE);
+        r.draw_rect(x + gap_x, y + gap_y, w, h, color);
+    }
+
+    /// move onto the cell the segment in front just left
+    pub fn update(&mut self, col: i32, row: i32) {
+        if !self.is_active {
+            return;
+        }
+
+        self.prev_cell = self.cell;
+        self.cell = (col, row);
+    }
+}
+
+// -----------------------------------
+// SNAKE
+// -----------------------------------
+pub struct Snake {
+    pub head: Head,
+    pub tail: Vec<Tail>,
+    /// add a segment on the next update
+    pub grow_pending: bool,
+}
+
+impl Snake {
+    pub fn from_cell(col: i32, row: i32) -> Self {
+        Self {
+            head: Head::from_cell(col, row, (TILE_W, TILE_H), Direction::Right),
+            tail: vec![],
+            grow_pending: false,
+        }
+    }
+
+    /// snake with no tail on a cell, heading `dir`
+    pub fn from_cell_facing(col: i32, row: i32, dir: Direction) -> Self {
+        let mut snake = Self::from_cell(col, row);
+        snake.head.set_direction(dir);
+        snake
+    }
+
+    /// snake heading `dir` with its head on `cell` and `len - 1` segments in
+    /// a line behind it, a `len` of 0 or 1 is a lone head
+    pub fn with_length(cell: (i32, i32), dir: Direction, len: usize) -> Self {
+        let mut snake = Self::from_cell_facing(cell.0, cell.1, dir);
+        snake.tail = body_cells(cell, dir, len)
+            .skip(1)
+            .map(|(x, y)| Tail::from_cell(x, y, (TILE_W, TILE_H)))
+            .collect();
+        snake
+    }
+
+    pub fn input(&mut self, keys: &KeyState) {
+        self.head.inputs(keys);
+    }
+
+    /// grow by one segment on the next update
+    pub fn grow(&mut self) {
+        self.grow_pending = true;
+    }
+
+    /// drop up to `n` segments from the end of the tail
+    pub fn shrink(&mut self, n: usize) {
+        let len = self.tail.len().saturating_sub(n);
+        self.tail.truncate(len);
+    }
+
+    /// move one tile, returns the cell the snake just left
+    pub fn update(&mut self, wrap: Option<(i32, i32)>) -> (i32, i32) {
+        // store last position
+        let (mut prev_x, mut prev_y) = self.head.cell();
+        self.head.update(wrap);
+
+        for t in self.tail.iter_mut() {
+            // store last position
+            let (prev_tx, prev_ty) = t.cell();
+            t.update(prev_x, prev_y);
+            prev_x = prev_tx;
+            prev_y = prev_ty;
+        }
+
+        if self.grow_pending {
+            // prev_x and prev_y should be last tail seg prev x and y
+            let new_seg = Tail::from_cell(prev_x, prev_y, (TILE_W, TILE_H));
+            self.tail.push(new_seg);
+            self.grow_pending = false;
+        }
+
+        (prev_x, prev_y)
+    }
+
+    /// draw `progress` (0..1) of the way through the last move,
+    /// 1.0 draws every piece on its cell
+    pub fn draw(&self, theme: &Theme, progress: f32, r: &mut dyn Renderer) {
+        self.head.draw(theme, progress, r);
+        // fade from the head color at the neck to the tail color at the tip
+        let len = self.tail.len() as f32;
+        for (i, t) in self.tail.iter().enumerate() {
+            let color = lerp_color(theme.head, theme.tail, (i + 1) as f32 / len);
+            t.draw(color, progress, r);
+        }
+    }
+
+    /// draw, or not on odd `invulnerable_ticks` while `blinking`. the ticks
+    /// only count down in play, so a countdown passes false to keep the
+    /// snake in view
+    pub fn draw_blinking(
+        &self,
+        theme: &Theme,
+        progress: f32,
+        invulnerable_ticks: u32,
+        blinking: bool,
+        r: &mut dyn Renderer,
+    ) {
+        if blinking && !invulnerable_ticks.is_multiple_of(2) {
+            return;
+        }
+        self.draw(theme, progress, r);
+    }
+
+    /// tile the head is on
+    pub fn head_tile(&self) -> (i32, i32) {
+        self.head.cell()
+    }
+
+    /// true if any part of the snake is on tile x, y
+    pub fn occupies(&self, x: i32, y: i32) -> bool {
+        self.head_tile() == (x, y) || self.tail_occupies(x, y)
+    }
+
+    pub fn tail_occupies(&self, x: i32, y: i32) -> bool {
+        self.tail.iter().any(|t| t.cell() == (x, y))
+    }
+
+    /// true if the head is on the same tile as one of the tail segments.
+    ///
+    /// only meaningful between moves, `update` shifts the head and every
+    /// segment in one go so a segment leaving a tile never counts. the neck
+    /// (first segment) is skipped, it always sits on the tile the head just
+    /// left and reversing onto it is blocked by `can_turn`.
+    pub fn self_collides(&self) -> bool {
+        let head = self.head_tile();
+        self.tail.iter().skip(1).any(|t| t.cell() == head)
+    }
+}
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// the `len` cells of a straight snake heading `dir`, head first
+pub fn body_cells(
+    head: (i32, i32),
+    dir: Direction,
+    len: usize,
+) -> impl Iterator<Item = (i32, i32)> {
+    let (dx, dy) = dir.offset();
+    (0..len.max(1) as i32).map(move |i| (head.0 - dx * i, head.1 - dy * i))
+}
+
+/// wrap a grid coordinate into 0..size
+pub fn wrap_coord(v: i32, size: i32) -> i32 {
+    if size <= 0 {
+        return v;
+    }
+    v.rem_euclid(size)
+}
+
+/// top left screen position of a grid cell of (w, h) px tiles
+pub fn cell_to_screen(cell: (i32, i32), size: (f32, f32)) -> (f32, f32) {
+    (cell.0 as f32 * size.0, cell.1 as f32 * size.1)
+}
+
+/// top left screen position `t` (0..1) of the way from cell `from` to `to`.
+/// cells more than one step apart (wrapping round an edge) are not slid
+/// between, that would sweep across the whole map
+pub fn lerp_cell(from: (i32, i32), to: (i32, i32), t: f32, size: (f32, f32)) -> (f32, f32) {
+    let (tx, ty) = cell_to_screen(to, size);
+    if (to.0 - from.0).abs() > 1 || (to.1 - from.1).abs() > 1 {
+        return (tx, ty);
+    }
+
+    let (fx, fy) = cell_to_screen(from, size);
+    let t = t.clamp(0.0, 1.0);
+    (fx + (tx - fx) * t, fy + (ty - fy) * t)
+}
+
+/// top left of the two eyes inside a (w, h) px tile, pushed toward
+/// the front of a head facing `dir`
+pub fn eye_offsets(dir: Direction, size: (f32, f32)) -> [(f32, f32); 2] {
+    let (fx, fy) = dir.offset();
+    // side step, at right angles to facing
+    let (sx, sy) = (-fy as f32, fx as f32);
+    let (fx, fy) = (fx as f32, fy as f32);
+
+    // each axis scales with the tile's size along it
+    let (w, h) = size;
+    let eye_at = |side: f32| {
+        (
+            w / 2.0 + fx * w * 0.22 + sx * w * 0.2 * side - w * EYE_SCALE / 2.0,
+            h / 2.0 + fy * h * 0.22 + sy * h * 0.2 * side - h * EYE_SCALE / 2.0,
+        )
+    };
+    [eye_at(-1.0), eye_at(1.0)]
+}
+
+/// false only if `requested` would double the snake straight back on itself
+pub fn can_turn(current: Direction, requested: Direction) -> bool {
+    requested != current.opposite()
+}
+
+/// (gap, size) of a segment `segment_scale` of a tile across, centered in
+/// the tile `gap` px in from its corner
+pub fn fit_segment(tile_size: f32, segment_scale: f32) -> (f32, f32) {
+    let size = tile_size * segment_scale.clamp(0.0, 1.0);
+    let gap = (tile_size - size) / 2.0;
+    (gap, size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::mock::{Drawn, MockRenderer};
+    use crate::theme::ThemeName;
+
+    #[test]
+    fn segment_is_centered_in_its_tile() {
+        let (gap, size) = fit_segment(25.0, 0.5);
+        assert_eq!(size, 12.5);
+        assert_eq!(gap, 6.25);
+        assert_eq!(gap * 2.0 + size, 25.0);
+    }
+
+    #[test]
+    fn segment_scale_is_clamped() {
+        assert_eq!(fit_segment(20.0, 1.0), (0.0, 20.0));
+        assert_eq!(fit_segment(20.0, 1.5), (0.0, 20.0));
+        assert_eq!(fit_segment(20.0, -1.0), (10.0, 0.0));
+    }
+
+    #[test]
+    fn tail_draws_its_tile() {
+        let tail = Tail::from_cell(3, 2, (20.0, 30.0));
+        let mut r = MockRenderer::new(800.0, 600.0);
+        tail.draw(Color::GREEN, 1.0, &mut r);
+        let (gap_x, w) = fit_segment(20.0, SEGMENT_SCALE);
+        let (gap_y, h) = fit_segment(30.0, SEGMENT_SCALE);
+        let rect = Drawn::Rect(60.0 + gap_x, 60.0 + gap_y, w, h, Color::GREEN);
+        assert_eq!(r.drawn, vec![rect]);
+    }
+
+    #[test]
+    fn held_up_turns_the_head_up() {
+        let mut head = Head::from_cell(5, 5, (TILE_W, TILE_H), Direction::Right);
+        let mut keys = KeyState::default();
+        keys.on_key_down(Action::Up);
+        head.inputs(&keys);
+        head.update(None);
+        assert_eq!(head.dir, Direction::Up);
+        assert_eq!(head.cell(), (5, 4));
+    }
+
+    #[test]
+    fn held_reverse_is_ignored() {
+        let mut head = Head::from_cell(5, 5, (TILE_W, TILE_H), Direction::Right);
+        let mut keys = KeyState::default();
+        keys.on_key_down(Action::Left);
+        head.inputs(&keys);
+        head.update(None);
+        assert_eq!(head.dir, Direction::Right);
+    }
+
+    #[test]
+    fn wrap_coord_at_each_edge() {
+        assert_eq!(wrap_coord(-1, 10), 9);
+        assert_eq!(wrap_coord(10, 10), 0);
+        assert_eq!(wrap_coord(0, 10), 0);
+        assert_eq!(wrap_coord(9, 10), 9);
+        assert_eq!(wrap_coord(-11, 10), 9);
+        // no size to wrap in
+        assert_eq!(wrap_coord(-1, 0), -1);
+    }
+
+    #[test]
+    fn head_wraps_off_every_edge() {
+        let cases = [
+            ((0, 3), Direction::Left, (7, 3)),
+            ((7, 3), Direction::Right, (0, 3)),
+            ((3, 0), Direction::Up, (3, 4)),
+            ((3, 4), Direction::Down, (3, 0)),
+        ];
+        for &(start, dir, end) in cases.iter() {
+            let mut head = Head::from_cell(start.0, start.1, (TILE_W, TILE_H), dir);
+            head.update(Some((8, 5)));
+            assert_eq!(head.cell(), end, "{:?} from {:?}", dir, start);
+        }
+    }
+
+    #[test]
+    fn can_turn_every_way_but_back() {
+        let dirs = [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ];
+        for &current in dirs.iter() {
+            for &requested in dirs.iter() {
+                let reverse = matches!(
+                    (current, requested),
+                    (Direction::Up, Direction::Down)
+                        | (Direction::Down, Direction::Up)
+                        | (Direction::Left, Direction::Right)
+                        | (Direction::Right, Direction::Left)
+                );
+                assert_eq!(
+                    can_turn(current, requested),
+                    !reverse,
+                    "{:?} to {:?}",
+                    current,
+                    requested
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn opposite_undoes_itself() {
+        for &dir in [Direction::Up, Direction::Left].iter() {
+            assert_ne!(dir.opposite(), dir);
+            assert_eq!(dir.opposite().opposite(), dir);
+        }
+    }
+
+    #[test]
+    fn straight_snake_does_not_collide() {
+        let mut snake = Snake::with_length((5, 5), Direction::Right, 6);
+        assert!(!snake.self_collides());
+        snake.update(None);
+        assert!(!snake.self_collides());
+    }
+
+    #[test]
+    fn neck_behind_the_head_does_not_count() {
+        let snake = Snake::with_length((5, 5), Direction::Right, 2);
+        assert_eq!(snake.tail[0].cell(), (4, 5));
+        assert!(!snake.self_collides());
+    }
+
+    #[test]
+    fn curled_snake_collides() {
+        let mut snake = Snake::with_length((5, 5), Direction::Right, 5);
+        for &dir in [Direction::Down, Direction::Left, Direction::Up].iter() {
+            snake.head.set_direction(dir);
+            snake.update(None);
+        }
+        assert_eq!(snake.head_tile(), (4, 5));
+        assert!(snake.self_collides());
+    }
+
+    #[test]
+    fn cell_to_screen_uses_each_axis_size() {
+        let size = (20.0, 30.0);
+        assert_eq!(cell_to_screen((0, 0), size), (0.0, 0.0));
+        assert_eq!(cell_to_screen((3, 2), size), (60.0, 60.0));
+        assert_eq!(cell_to_screen((2, 3), size), (40.0, 90.0));
+    }
+
+    #[test]
+    fn lerp_cell_with_wide_and_tall_tiles() {
+        let size = (20.0, 30.0);
+        assert_eq!(lerp_cell((1, 1), (2, 1), 0.5, size), (30.0, 30.0));
+        assert_eq!(lerp_cell((1, 1), (1, 2), 0.5, size), (20.0, 45.0));
+        assert_eq!(lerp_cell((1, 1), (1, 2), 2.0, size), (20.0, 60.0));
+        // wrapping round an edge jumps straight there
+        assert_eq!(lerp_cell((9, 1), (0, 1), 0.5, size), (0.0, 30.0));
+    }
+
+    #[test]
+    fn eyes_stay_inside_a_non_square_tile() {
+        let size = (20.0, 30.0);
+        for &dir in [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+        .iter()
+        {
+            for &(x, y) in eye_offsets(dir, size).iter() {
+                assert!(
+                    (0.0..20.0).contains(&x) && (0.0..30.0).contains(&y),
+                    "{:?}",
+                    dir
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn respawned_snake_shows_through_the_countdown() {
+        let theme = ThemeName::Classic.theme();
+        let snake = Snake::with_length((4, 4), Direction::Right, 3);
+        // an odd count, as a respawn leaves it
+        let mut r = MockRenderer::new(800.0, 600.0);
+        snake.draw_blinking(&theme, 1.0, 15, false, &mut r);
+        assert!(!r.drawn.is_empty());
+    }
+
+    #[test]
+    fn invulnerable_snake_blinks_in_play() {
+        let theme = ThemeName::Classic.theme();
+        let snake = Snake::with_length((4, 4), Direction::Right, 3);
+        let mut r = MockRenderer::new(800.0, 600.0);
+        snake.draw_blinking(&theme, 1.0, 15, true, &mut r);
+        assert!(r.drawn.is_empty());
+        snake.draw_blinking(&theme, 1.0, 14, true, &mut r);
+        assert!(!r.drawn.is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+//! where new food, poison and other items land on the map
+
+use crate::map::Map;
+use crate::snake::Snake;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// -----------------------------------
+// WEIGHTING
+// -----------------------------------
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpawnWeighting {
+    /// every free tile is equally likely
+    Uniform,
+    /// free tiles near the middle of the map are more likely
+    CenterBiased,
+    /// free tiles near the edges of the map are more likely
+    EdgeBiased,
+}
+
+impl SpawnWeighting {
+    /// relative chance (0..1) of a free tile at x, y on a rows x cols map being picked
+    pub fn weight(&self, x: i32, y: i32, rows: i32, cols: i32) -> f32 {
+        // 0.0 at the center, 1.0 on the outer ring
+        let half_w = (rows as f32 / 2.0).max(1.0);
+        let half_h = (cols as f32 / 2.0).max(1.0);
+        let dx = ((x as f32 + 0.5) - half_w).abs() / half_w;
+        let dy = ((y as f32 + 0.5) - half_h).abs() / half_h;
+        let dist = dx.max(dy).min(1.0);
+
+        match self {
+            SpawnWeighting::Uniform => 1.0,
+            SpawnWeighting::CenterBiased => 1.0 - 0.9 * dist,
+            SpawnWeighting::EdgeBiased => 0.1 + 0.9 * dist,
+        }
+    }
+}
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// rng for a run, the same seed gives the same food / poison sequence
+pub fn make_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+pub fn rand_range(rng: &mut StdRng, min_value: i32, max_value: i32) -> i32 {
+    use std::cmp::{max, min};
+
+    let min_v = min(min_value, max_value);
+    let max_v = max(min_value, max_value);
+
+    let result: i32 = rng.gen_range(min_v, max_v);
+
+    result
+}
+
+/// random value in 0..1
+pub fn rand_unit(rng: &mut StdRng) -> f32 {
+    rng.gen::<f32>()
+}
+
+/// nothing on x, y that an item would cover or land on: no snake, wall,
+/// portal or other item
+pub fn is_free_tile(x: i32, y: i32, snakes: &[&Snake], map_data: &Map) -> bool {
+    // only the exact cell is taken, sharing a row or column is fine
+    !snakes.iter().any(|s| s.occupies(x, y))
+        && !map_data.is_tile_blocked(x, y)
+        // an item would overwrite the portal
+        && map_data.portal_at(x, y).is_none()
+        && !map_data.is_tile_active(x, y)
+        && !map_data.is_tile_poison(x, y)
+        && !map_data.is_tile_bonus(x, y)
+        && !map_data.is_tile_shrink(x, y)
+        && !map_data.is_tile_boost(x, y)
+}
+
+/// a free tile inside the outer ring of the map, picked among all of them
+/// by `weighting`. None once the board is full
+pub fn new_random_tile(
+    rng: &mut StdRng,
+    rows: i32,
+    cols: i32,
+    snakes: &[&Snake],
+    map_data: &Map,
+    weighting: SpawnWeighting,
+) -> Option<(i32, i32)> {
+    let free: Vec<(i32, i32)> = (1..cols - 1)
+        .flat_map(|y| (1..rows - 1).map(move |x| (x, y)))
+        .filter(|&(x, y)| is_free_tile(x, y, snakes, map_data))
+        .collect();
+    if free.is_empty() {
+        return None;
+    }
+
+    let weights: Vec<f32> = free
+        .iter()
+        .map(|&(x, y)| weighting.weight(x, y, rows, cols))
+        .collect();
+    let total: f32 = weights.iter().sum();
+    if total <= 0.0 {
+        return Some(free[rand_range(rng, 0, free.len() as i32) as usize]);
+    }
+
+    let mut pick = rand_unit(rng) * total;
+    for (&cell, &w) in free.iter().zip(weights.iter()) {
+        if pick < w {
+            return Some(cell);
+        }
+        pick -= w;
+    }
+    // float rounding can leave pick just past the last weight
+    free.last().copied()
+}
+
+/// a free cell from the map's bonus zones, anywhere free if it has none
+pub fn bonus_spawn_tile(
+    rng: &mut StdRng,
+    rows: i32,
+    cols: i32,
+    snakes: &[&Snake],
+    map_data: &Map,
+    zones: &[(i32, i32)],
+    weighting: SpawnWeighting,
+) -> Option<(i32, i32)> {
+    let free: Vec<(i32, i32)> = zones
+        .iter()
+        .copied()
+        .filter(|&(x, y)| is_free_tile(x, y, snakes, map_data))
+        .collect();
+    if free.is_empty() {
+        return new_random_tile(rng, rows, cols, snakes, map_data, weighting);
+    }
+    Some(free[rand_range(rng, 0, free.len() as i32) as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::blank_map;
+    use crate::snake::Direction;
+
+    /// share of `samples` picks on an empty 12x12 map that land in the
+    /// middle 4x4, and what `weighting` says that share should be
+    fn middle_share(weighting: SpawnWeighting, samples: usize) -> (f32, f32) {
+        let map = Map::new(12, 12, blank_map(12, 12));
+        let middle = |x: i32, y: i32| (4..8).contains(&x) && (4..8).contains(&y);
+
+        let mut total = 0.0;
+        let mut in_middle = 0.0;
+        for y in 1..11 {
+            for x in 1..11 {
+                let w = weighting.weight(x, y, 12, 12);
+                total += w;
+                if middle(x, y) {
+                    in_middle += w;
+                }
+            }
+        }
+
+        let mut rng = make_rng(Some(7));
+        let hits = (0..samples)
+            .filter_map(|_| new_random_tile(&mut rng, 12, 12, &[], &map, weighting))
+            .filter(|&(x, y)| middle(x, y))
+            .count();
+        (hits as f32 / samples as f32, in_middle / total)
+    }
+
+    /// the first `n` tiles picked on an empty map by an rng from `seed`
+    fn tiles_from(seed: u64, n: usize) -> Vec<(i32, i32)> {
+        let map = Map::new(16, 12, blank_map(16, 12));
+        let mut rng = make_rng(Some(seed));
+        (0..n)
+            .filter_map(|_| new_random_tile(&mut rng, 16, 12, &[], &map, SpawnWeighting::Uniform))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_tiles() {
+        assert_eq!(tiles_from(42, 20), tiles_from(42, 20));
+        assert_ne!(tiles_from(42, 20), tiles_from(43, 20));
+    }
+
+    #[test]
+    fn rand_range_either_way_round() {
+        let mut rng = make_rng(Some(1));
+        for _ in 0..100 {
+            let v = rand_range(&mut rng, 8, 3);
+            assert!((3..8).contains(&v));
+        }
+    }
+
+    #[test]
+    fn never_lands_on_the_snake_but_can_share_its_row_and_column() {
+        let map = Map::new(8, 8, blank_map(8, 8));
+        let snake = Snake::with_length((4, 3), Direction::Right, 3);
+        let body = [(4, 3), (3, 3), (2, 3)];
+        let mut rng = make_rng(Some(3));
+        let mut shared_line = false;
+        for _ in 0..500 {
+            let (x, y) =
+                new_random_tile(&mut rng, 8, 8, &[&snake], &map, SpawnWeighting::Uniform).unwrap();
+            assert!(
+                !body.contains(&(x, y)),
+                "landed on the snake at {}, {}",
+                x,
+                y
+            );
+            shared_line |= y == 3 || x == 4;
+        }
+        assert!(shared_line);
+    }
+
+    #[test]
+    fn picks_follow_the_weighting() {
+        for &weighting in [
+            SpawnWeighting::Uniform,
+            SpawnWeighting::CenterBiased,
+            SpawnWeighting::EdgeBiased,
+        ]
+        .iter()
+        {
+            let (seen, expected) = middle_share(weighting, 5_000);
+            assert!(
+                (seen - expected).abs() < 0.03,
+                "{:?}: {} in the middle, expected {}",
+                weighting,
+                seen,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn center_bias_favours_the_middle() {
+        let w = SpawnWeighting::CenterBiased;
+        assert!(w.weight(5, 5, 12, 12) > w.weight(1, 1, 12, 12));
+        let w = SpawnWeighting::EdgeBiased;
+        assert!(w.weight(5, 5, 12, 12) < w.weight(1, 1, 12, 12));
+    }
+
+    #[test]
+    fn one_free_cell_is_always_picked_and_none_is_none() {
+        // a 3x2 inside, the snake on the top row and walls on two of the
+        // bottom row leave only (3, 2)
+        let mut map = Map::new(5, 4, blank_map(5, 4));
+        map.block_tile(1, 2);
+        map.block_tile(2, 2);
+        let snake = Snake::with_length((3, 1), Direction::Right, 3);
+        let mut rng = make_rng(Some(5));
+        for _ in 0..20 {
+            let tile = new_random_tile(&mut rng, 5, 4, &[&snake], &map, SpawnWeighting::Uniform);
+            assert_eq!(tile, Some((3, 2)));
+        }
+
+        map.block_tile(3, 2);
+        let tile = new_random_tile(&mut rng, 5, 4, &[&snake], &map, SpawnWeighting::Uniform);
+        assert_eq!(tile, None);
+    }
+}
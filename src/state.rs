@@ -0,0 +1,104 @@
+//! which screen the app is on
+
+// -----------------------------------
+// APP STATE
+// -----------------------------------
+/// what the app is doing, drives which update / render branch runs
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum AppState {
+    MapSelect,
+    Menu,
+    Countdown,
+    Playing,
+    Paused,
+    GameOver,
+    Won,
+    /// typing a name for a score that made the leaderboard
+    NameEntry,
+    Leaderboard,
+    Stats,
+    /// picking new keys for each action
+    Controls,
+}
+
+impl AppState {
+    /// Playing <-> Paused, any other state is left as is
+    pub fn toggle_pause(self) -> Self {
+        match self {
+            AppState::Playing => AppState::Paused,
+            AppState::Paused => AppState::Playing,
+            other => other,
+        }
+    }
+
+    /// is there a game in progress for F5 to save
+    pub fn can_save(self) -> bool {
+        matches!(
+            self,
+            AppState::Countdown | AppState::Playing | AppState::Paused
+        )
+    }
+
+    /// can F9 load over what's on screen, the menu or a game in progress.
+    /// never a finished run, that would be a free continue
+    pub fn can_load(self) -> bool {
+        self == AppState::Menu || self.can_save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_and_back() {
+        let paused = AppState::Playing.toggle_pause();
+        assert_eq!(paused, AppState::Paused);
+        assert_eq!(paused.toggle_pause(), AppState::Playing);
+    }
+
+    #[test]
+    fn only_a_game_in_play_pauses() {
+        let others = [
+            AppState::MapSelect,
+            AppState::Menu,
+            AppState::Countdown,
+            AppState::GameOver,
+            AppState::Won,
+            AppState::NameEntry,
+            AppState::Leaderboard,
+            AppState::Stats,
+            AppState::Controls,
+        ];
+        for &state in others.iter() {
+            assert_eq!(state.toggle_pause(), state);
+        }
+    }
+
+    #[test]
+    fn only_a_game_in_progress_saves() {
+        for &state in [AppState::Countdown, AppState::Playing, AppState::Paused].iter() {
+            assert!(state.can_save(), "{:?}", state);
+            assert!(state.can_load(), "{:?}", state);
+        }
+        assert!(!AppState::Menu.can_save());
+        assert!(AppState::Menu.can_load());
+    }
+
+    #[test]
+    fn finished_runs_neither_save_nor_load() {
+        let finished = [
+            AppState::MapSelect,
+            AppState::GameOver,
+            AppState::Won,
+            AppState::NameEntry,
+            AppState::Leaderboard,
+            AppState::Stats,
+            AppState::Controls,
+        ];
+        for &state in finished.iter() {
+            assert!(!state.can_save(), "{:?}", state);
+            assert!(!state.can_load(), "{:?}", state);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! totals kept across every game played
+
+use std::fs::File;
+use std::io::{Result, Write};
+
+// -----------------------------------
+// STATS
+// -----------------------------------
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub games_played: u32,
+    pub food_eaten: u32,
+    /// most tiles a snake has covered, head included
+    pub longest_snake: u32,
+    pub best_score: u32,
+    /// ms spent playing, pauses and menus left out
+    pub played_ms: u64,
+}
+
+impl Stats {
+    /// add a finished game to the totals
+    pub fn record_game(&mut self, food_eaten: u32, longest_snake: u32, score: u32, played_ms: u64) {
+        self.games_played += 1;
+        self.food_eaten += food_eaten;
+        self.longest_snake = self.longest_snake.max(longest_snake);
+        self.best_score = self.best_score.max(score);
+        self.played_ms += played_ms;
+    }
+}
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// `name value` per line, all zeros if missing, a bad value reads as zero
+pub fn load_stats(path: &str) -> Stats {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(_) => return Stats::default(),
+    };
+
+    let mut stats = Stats::default();
+    for line in text.lines() {
+        let mut parts = line.split_whitespace();
+        let (name, value) = match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) => (name, value),
+            _ => continue,
+        };
+        match name {
+            "games_played" => stats.games_played = value.parse().unwrap_or(0),
+            "food_eaten" => stats.food_eaten = value.parse().unwrap_or(0),
+            "longest_snake" => stats.longest_snake = value.parse().unwrap_or(0),
+            "best_score" => stats.best_score = value.parse().unwrap_or(0),
+            "played_ms" => stats.played_ms = value.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    stats
+}
+
+pub fn save_stats(path: &str, stats: &Stats) -> Result<()> {
+    let mut file = File::create(path)?;
+    writeln!(file, "games_played {}", stats.games_played)?;
+    writeln!(file, "food_eaten {}", stats.food_eaten)?;
+    writeln!(file, "longest_snake {}", stats.longest_snake)?;
+    writeln!(file, "best_score {}", stats.best_score)?;
+    writeln!(file, "played_ms {}", stats.played_ms)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a path of its own in the temp dir
+    fn stats_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("rust_snake_test_{}.txt", name));
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let path = stats_path("stats_round_trip");
+        let mut stats = Stats::default();
+        stats.record_game(7, 9, 70, 42_000);
+        stats.record_game(2, 4, 20, 8_000);
+        save_stats(&path, &stats).unwrap();
+        assert_eq!(load_stats(&path), stats);
+    }
+
+    #[test]
+    fn missing_file_is_all_zeros() {
+        assert_eq!(load_stats(&stats_path("stats_missing")), Stats::default());
+    }
+
+    #[test]
+    fn bad_values_read_as_zero_and_unknown_lines_are_skipped() {
+        let path = stats_path("stats_bad");
+        std::fs::write(
+            &path,
+            "games_played lots\nfood_eaten 5\nhats 3\nbest_score\n",
+        )
+        .unwrap();
+        let stats = load_stats(&path);
+        assert_eq!(stats.games_played, 0);
+        assert_eq!(stats.food_eaten, 5);
+        assert_eq!(stats.best_score, 0);
+    }
+
+    #[test]
+    fn records_totals_and_bests() {
+        let mut stats = Stats::default();
+        stats.record_game(3, 10, 30, 1_000);
+        stats.record_game(1, 6, 50, 500);
+        assert_eq!(stats.games_played, 2);
+        assert_eq!(stats.food_eaten, 4);
+        assert_eq!(stats.longest_snake, 10);
+        assert_eq!(stats.best_score, 50);
+        assert_eq!(stats.played_ms, 1_500);
+    }
+}
@@ -0,0 +1,225 @@
+//! color themes for the map, items and the snake
+
+use sfml::graphics::Color;
+
+// -----------------------------------
+// ENUMS
+// -----------------------------------
+/// theme presets, cycled in play
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ThemeName {
+    Classic,
+    Dark,
+    HighContrast,
+}
+
+impl ThemeName {
+    /// the preset after this one, wrapping back to the first
+    pub fn next(&self) -> ThemeName {
+        match self {
+            ThemeName::Classic => ThemeName::Dark,
+            ThemeName::Dark => ThemeName::HighContrast,
+            ThemeName::HighContrast => ThemeName::Classic,
+        }
+    }
+
+    /// preset from its snake_case name, as written in the config file
+    pub fn from_name(name: &str) -> Option<ThemeName> {
+        match name {
+            "classic" => Some(ThemeName::Classic),
+            "dark" => Some(ThemeName::Dark),
+            "high_contrast" => Some(ThemeName::HighContrast),
+            _ => None,
+        }
+    }
+
+    pub fn theme(&self) -> Theme {
+        match self {
+            ThemeName::Classic => Theme {
+                background: Color::WHITE,
+                floor: Color::rgb(21, 21, 21),
+                wall: Color::BLACK,
+                wall_edge: Color::rgb(110, 110, 110),
+                wall_edge_px: 2.0,
+                obstacle: Color::rgb(90, 90, 90),
+                food: Color::GREEN,
+                // close enough to food to make the player look twice
+                poison: Color::rgb(120, 220, 0),
+                bonus: Color::rgb(255, 200, 0),
+                shrink: Color::rgb(40, 120, 255),
+                boost: Color::rgb(200, 60, 200),
+                head: Color::WHITE,
+                tail: Color::RED,
+                rival_head: Color::rgb(200, 230, 255),
+                rival_tail: Color::BLUE,
+                hazard: Color::rgb(255, 110, 0),
+                portal: Color::rgb(170, 60, 255),
+            },
+            ThemeName::Dark => Theme {
+                background: Color::BLACK,
+                floor: Color::rgb(10, 10, 14),
+                wall: Color::rgb(45, 45, 60),
+                wall_edge: Color::rgb(95, 95, 125),
+                wall_edge_px: 2.0,
+                obstacle: Color::rgb(75, 75, 95),
+                food: Color::rgb(80, 200, 120),
+                poison: Color::rgb(150, 175, 40),
+                bonus: Color::rgb(230, 180, 40),
+                shrink: Color::rgb(70, 110, 220),
+                boost: Color::rgb(160, 60, 160),
+                head: Color::rgb(220, 220, 230),
+                tail: Color::rgb(170, 60, 60),
+                rival_head: Color::rgb(200, 220, 240),
+                rival_tail: Color::rgb(60, 90, 170),
+                hazard: Color::rgb(200, 90, 30),
+                portal: Color::rgb(140, 70, 200),
+            },
+            // food and poison differ in brightness as well as hue, and
+            // neither is close to the snake, for colorblind players
+            ThemeName::HighContrast => Theme {
+                background: Color::BLACK,
+                floor: Color::BLACK,
+                wall: Color::rgb(100, 100, 100),
+                wall_edge: Color::WHITE,
+                wall_edge_px: 3.0,
+                obstacle: Color::rgb(160, 160, 160),
+                food: Color::YELLOW,
+                poison: Color::MAGENTA,
+                bonus: Color::rgb(255, 140, 0),
+                shrink: Color::rgb(0, 200, 255),
+                boost: Color::rgb(190, 110, 255),
+                head: Color::WHITE,
+                tail: Color::rgb(0, 90, 255),
+                rival_head: Color::rgb(255, 200, 200),
+                rival_tail: Color::rgb(230, 0, 0),
+                hazard: Color::WHITE,
+                portal: Color::CYAN,
+            },
+        }
+    }
+}
+
+// -----------------------------------
+// THEME
+// -----------------------------------
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    /// window clear color behind the map
+    pub background: Color,
+    /// open tiles
+    pub floor: Color,
+    pub wall: Color,
+    /// border round each wall tile so walls stand out from the floor
+    pub wall_edge: Color,
+    /// px thickness of that border
+    pub wall_edge_px: f32,
+    pub obstacle: Color,
+    pub food: Color,
+    pub poison: Color,
+    pub bonus: Color,
+    pub shrink: Color,
+    pub boost: Color,
+    pub head: Color,
+    /// color at the tail tip, segments fade into it from `head`
+    pub tail: Color,
+    /// player two's head and tail tip in versus
+    pub rival_head: Color,
+    pub rival_tail: Color,
+    /// moving obstacles
+    pub hazard: Color,
+    pub portal: Color,
+}
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// color `t` (clamped to 0..1) of the way from `a` to `b`
+pub fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    let mix = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t).round() as u8;
+    Color::rgba(mix(a.r, b.r), mix(a.g, b.g), mix(a.b, b.b), mix(a.a, b.a))
+}
+
+/// how strongly (0..1) to play the finale effect for a score
+/// within `margin` points of the high score
+pub fn finale_intensity(score: u32, high_score: u32, margin: u32) -> f32 {
+    if high_score == 0 {
+        return 0.0;
+    }
+    if score >= high_score {
+        return 1.0;
+    }
+
+    let gap = high_score - score;
+    if gap > margin {
+        return 0.0;
+    }
+
+    1.0 - gap as f32 / (margin + 1) as f32
+}
+
+/// head color for the finale, pulsing from `base` faster and redder
+/// as intensity rises
+pub fn finale_color(base: Color, intensity: f32, seconds: f32) -> Color {
+    if intensity <= 0.0 {
+        return base;
+    }
+
+    let pulse = 0.5 + 0.5 * (seconds * (4.0 + 8.0 * intensity)).sin();
+    let k = (intensity * pulse).min(1.0);
+    let towards = |from: u8, to: f32| (from as f32 + (to - from as f32) * k) as u8;
+    Color::rgb(
+        towards(base.r, 255.0),
+        towards(base.g, 0.0),
+        towards(base.b, base.b as f32 / 4.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lerp_color_midpoint() {
+        let a = Color::rgba(0, 100, 200, 255);
+        let b = Color::rgba(200, 100, 0, 55);
+        assert_eq!(lerp_color(a, b, 0.5), Color::rgba(100, 100, 100, 155));
+    }
+
+    #[test]
+    fn lerp_color_ends_and_clamping() {
+        let (a, b) = (Color::BLACK, Color::WHITE);
+        assert_eq!(lerp_color(a, b, 0.0), a);
+        assert_eq!(lerp_color(a, b, 1.0), b);
+        assert_eq!(lerp_color(a, b, -2.0), a);
+        assert_eq!(lerp_color(a, b, 3.0), b);
+    }
+
+    #[test]
+    fn no_finale_without_a_high_score() {
+        assert_eq!(finale_intensity(0, 0, 5), 0.0);
+        assert_eq!(finale_intensity(40, 0, 5), 0.0);
+    }
+
+    #[test]
+    fn finale_builds_up_inside_the_margin() {
+        assert_eq!(finale_intensity(10, 20, 5), 0.0);
+        assert_eq!(finale_intensity(14, 20, 5), 0.0);
+        let far = finale_intensity(15, 20, 5);
+        let near = finale_intensity(19, 20, 5);
+        assert!(far > 0.0 && far < near && near < 1.0, "{} {}", far, near);
+        assert!((near - 5.0 / 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn finale_is_full_at_and_past_the_high_score() {
+        assert_eq!(finale_intensity(20, 20, 5), 1.0);
+        assert_eq!(finale_intensity(30, 20, 5), 1.0);
+    }
+
+    #[test]
+    fn no_intensity_keeps_the_base_color() {
+        let base = Color::rgb(10, 200, 30);
+        assert_eq!(finale_color(base, 0.0, 3.0), base);
+    }
+}
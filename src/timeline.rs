@@ -0,0 +1,71 @@
+//! per tick debug log enabled with --trace
+
+use crate::snake::Direction;
+use std::fs::File;
+use std::io::{Result, Write};
+
+// -----------------------------------
+// TIMELINE
+// -----------------------------------
+/// every move the snake makes and everything that happens to it, each
+/// line marked with the tick and cell it happened on
+#[derive(Default)]
+pub struct Timeline {
+    pub lines: Vec<String>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// note an event as `tick name (x,y)`, `cell` being where it happened
+    pub fn event(&mut self, tick: usize, name: &str, cell: (i32, i32)) {
+        let (x, y) = cell;
+        self.lines.push(format!("{} {} ({},{})", tick, name, x, y));
+    }
+
+    /// note a move as `tick dir (x,y)`, the head's cell once it's made
+    pub fn record(&mut self, tick: usize, dir: Direction, cell: (i32, i32)) {
+        let (x, y) = cell;
+        self.lines.push(format!("{} {:?} ({},{})", tick, dir, x, y));
+    }
+
+    pub fn dump(&self, path: &str) -> Result<()> {
+        let mut file = File::create(path)?;
+        for line in self.lines.iter() {
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_keep_their_own_tick_and_cell() {
+        let mut tl = Timeline::new();
+        tl.record(1, Direction::Right, (4, 2));
+        tl.event(1, "eat", (4, 2));
+        tl.record(2, Direction::Down, (4, 3));
+        tl.event(2, "death", (4, 3));
+        assert_eq!(
+            tl.lines,
+            vec![
+                "1 Right (4,2)",
+                "1 eat (4,2)",
+                "2 Down (4,3)",
+                "2 death (4,3)"
+            ]
+        );
+    }
+
+    #[test]
+    fn events_are_not_held_for_the_next_move() {
+        let mut tl = Timeline::new();
+        tl.event(0, "boost", (1, 1));
+        assert_eq!(tl.lines, vec!["0 boost (1,1)"]);
+    }
+}
@@ -0,0 +1,275 @@
+//! how often the snake moves and the times shown on screen
+
+use sfml::system::Clock;
+
+// -----------------------------------
+// MOVE TIMER
+// -----------------------------------
+/// time counted towards the next move given as `clock_ms`, the ms on a clock
+/// restarted whenever the timer is. stops counting while paused
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct MoveTimer {
+    /// ms counted before the last pause, less any spent on moves
+    carried_ms: i32,
+    paused: bool,
+}
+
+impl MoveTimer {
+    pub fn elapsed_ms(&self, clock_ms: i32) -> i32 {
+        if self.paused {
+            return self.carried_ms;
+        }
+        self.carried_ms + clock_ms
+    }
+
+    /// spend `ms` of the time counted if there's that much, the rest carries
+    /// over so a long frame is made up by the next ones
+    pub fn take_ms(&mut self, ms: i32, clock_ms: i32) -> bool {
+        if self.elapsed_ms(clock_ms) < ms {
+            return false;
+        }
+        self.carried_ms -= ms;
+        true
+    }
+
+    /// is a move due: a press waiting in `requested` when `turn_based`, the
+    /// clock never moves the snake then, or `interval_ms` counted otherwise.
+    /// a press is used up by the move it makes
+    pub fn take_move(
+        &mut self,
+        turn_based: bool,
+        requested: &mut bool,
+        interval_ms: i32,
+        clock_ms: i32,
+    ) -> bool {
+        let due = if turn_based {
+            *requested
+        } else {
+            self.take_ms(interval_ms, clock_ms)
+        };
+        if due {
+            *requested = false;
+        }
+        due
+    }
+
+    /// back to 0 and counting, the clock restarts with it
+    pub fn restart(&mut self) {
+        self.carried_ms = 0;
+        self.paused = false;
+    }
+
+    pub fn pause(&mut self, clock_ms: i32) {
+        if !self.paused {
+            self.carried_ms += clock_ms;
+            self.paused = true;
+        }
+    }
+
+    /// true if it was paused, the clock restarts to count from now
+    pub fn resume(&mut self) -> bool {
+        let was_paused = self.paused;
+        self.paused = false;
+        was_paused
+    }
+}
+
+// -----------------------------------
+// MOVE CLOCK
+// -----------------------------------
+/// a MoveTimer counting real time
+pub struct MoveClock {
+    clock: Clock,
+    timer: MoveTimer,
+}
+
+impl MoveClock {
+    pub fn start() -> Self {
+        Self {
+            clock: Clock::start(),
+            timer: MoveTimer::default(),
+        }
+    }
+
+    fn clock_ms(&self) -> i32 {
+        self.clock.elapsed_time().as_milliseconds()
+    }
+
+    pub fn elapsed_ms(&self) -> i32 {
+        self.timer.elapsed_ms(self.clock_ms())
+    }
+
+    pub fn take_move(&mut self, turn_based: bool, requested: &mut bool, interval_ms: i32) -> bool {
+        let clock_ms = self.clock_ms();
+        self.timer
+            .take_move(turn_based, requested, interval_ms, clock_ms)
+    }
+
+    pub fn restart(&mut self) {
+        self.clock.restart();
+        self.timer.restart();
+    }
+
+    pub fn pause(&mut self) {
+        let clock_ms = self.clock_ms();
+        self.timer.pause(clock_ms);
+    }
+
+    pub fn resume(&mut self) {
+        if self.timer.resume() {
+            self.clock.restart();
+        }
+    }
+}
+
+// -----------------------------------
+// FUNCS
+// -----------------------------------
+/// ms as a `MM:SS` string, rounded up so a countdown only shows 0:00 at zero
+pub fn format_mm_ss(ms: i32) -> String {
+    let secs = (ms.max(0) + 999) / 1000;
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// ms between moves with a boost running or not, a boost scales it by
+/// `scale`, never below 1
+pub fn boost_interval(interval_ms: i32, boosted: bool, scale: f32) -> i32 {
+    if !boosted {
+        return interval_ms;
+    }
+    ((interval_ms as f32 * scale).round() as i32).max(1)
+}
+
+/// snake speed for a given ms tick interval
+pub fn tiles_per_second(interval_ms: i32) -> f32 {
+    if interval_ms <= 0 {
+        return 0.0;
+    }
+    1000.0 / interval_ms as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turn_based_moves_once_per_press() {
+        let mut timer = MoveTimer::default();
+        let mut moves = 0;
+        for _ in 0..5 {
+            let mut requested = true;
+            // however long it's been, a press is one move
+            for clock_ms in [0, 1_000, 60_000].iter() {
+                if timer.take_move(true, &mut requested, 100, *clock_ms) {
+                    moves += 1;
+                }
+            }
+        }
+        assert_eq!(moves, 5);
+    }
+
+    #[test]
+    fn turn_based_never_moves_on_its_own() {
+        let mut timer = MoveTimer::default();
+        let mut requested = false;
+        assert!(!timer.take_move(true, &mut requested, 100, 10_000));
+    }
+
+    #[test]
+    fn clocked_moves_ignore_presses() {
+        let mut timer = MoveTimer::default();
+        let mut requested = true;
+        assert!(!timer.take_move(false, &mut requested, 100, 50));
+        assert!(timer.take_move(false, &mut requested, 100, 100));
+        assert!(!requested);
+    }
+
+    #[test]
+    fn big_delta_moves_as_many_times_as_fit() {
+        let mut timer = MoveTimer::default();
+        let mut requested = false;
+        // one 350ms hitch is three 100ms moves with 50ms carried over
+        let moves = (0..10)
+            .filter(|_| timer.take_move(false, &mut requested, 100, 350))
+            .count();
+        assert_eq!(moves, 3);
+        assert_eq!(timer.elapsed_ms(350), 50);
+    }
+
+    /// moves a clocked timer makes over `wall_ms` checked once every
+    /// `frame_ms`, the way the game loop does
+    fn moves_at_frame_rate(frame_ms: i32, wall_ms: i32, interval_ms: i32) -> usize {
+        let mut timer = MoveTimer::default();
+        let mut requested = false;
+        (1..=wall_ms / frame_ms)
+            .filter(|frame| timer.take_move(false, &mut requested, interval_ms, frame * frame_ms))
+            .count()
+    }
+
+    #[test]
+    fn speed_is_the_same_at_60_and_30_fps() {
+        // 1584 ms is a whole number of 16 ms and 33 ms frames
+        for &interval in [40, 95, 100, 150].iter() {
+            let at_60 = moves_at_frame_rate(16, 1584, interval);
+            let at_30 = moves_at_frame_rate(33, 1584, interval);
+            assert_eq!(at_60, at_30, "{} ms moves", interval);
+            assert_eq!(at_60, 1584 / interval as usize);
+        }
+    }
+
+    #[test]
+    fn paused_time_does_not_count() {
+        let mut timer = MoveTimer::default();
+        timer.pause(60);
+        // however long the pause, the clock reading is ignored
+        assert_eq!(timer.elapsed_ms(5_000), 60);
+        assert!(!timer.take_ms(100, 5_000));
+        assert!(timer.resume());
+        assert!(!timer.resume());
+        // the clock restarts on resume, so it carries on from 60
+        assert!(!timer.take_ms(100, 30));
+        assert!(timer.take_ms(100, 40));
+    }
+
+    #[test]
+    fn mm_ss_from_ms() {
+        assert_eq!(format_mm_ss(0), "00:00");
+        assert_eq!(format_mm_ss(59_000), "00:59");
+        assert_eq!(format_mm_ss(60_000), "01:00");
+        assert_eq!(format_mm_ss(605_000), "10:05");
+    }
+
+    #[test]
+    fn mm_ss_rounds_up_part_seconds() {
+        assert_eq!(format_mm_ss(1), "00:01");
+        assert_eq!(format_mm_ss(59_001), "01:00");
+        assert_eq!(format_mm_ss(-500), "00:00");
+    }
+
+    #[test]
+    fn tiles_per_second_from_interval() {
+        assert_eq!(tiles_per_second(1000), 1.0);
+        assert_eq!(tiles_per_second(100), 10.0);
+        assert_eq!(tiles_per_second(250), 4.0);
+        assert!((tiles_per_second(150) - 6.667).abs() < 0.001);
+    }
+
+    #[test]
+    fn no_interval_is_no_speed() {
+        assert_eq!(tiles_per_second(0), 0.0);
+        assert_eq!(tiles_per_second(-40), 0.0);
+    }
+
+    #[test]
+    fn boost_shortens_the_interval() {
+        assert_eq!(boost_interval(100, false, 0.6), 100);
+        assert_eq!(boost_interval(100, true, 0.6), 60);
+        assert_eq!(boost_interval(95, true, 0.6), 57);
+    }
+
+    #[test]
+    fn boost_never_reaches_zero() {
+        assert_eq!(boost_interval(1, true, 0.6), 1);
+        assert_eq!(boost_interval(100, true, 0.0), 1);
+    }
+}